@@ -1,18 +1,37 @@
-use crate::config::Config;
-use crate::db::{match_property, DbConnection};
+use crate::config::{Config, ParseProfile};
+use crate::db::{fuzzy_match_property, match_property, metadata_source};
+use crate::debug_trace;
 use crate::detection::{
-    charset::convert_to_utf8, detect_charset, detect_data_type, detect_delimiter,
-    detect_email_column, detect_quote_char, has_header,
+    charset::{
+        charset_label, convert_to_utf8_max_errors, is_utf16, last_line_end, strip_nul_chars,
+        ConvertError, DecodedLines,
+    },
+    detect_charset, detect_column_types, detect_currency_column, detect_data_type,
+    detect_decimal_separator, detect_delimiter, detect_email_column, detect_quote_char,
+    detect_semantic_type, guess_locale, has_header, has_units_row, is_percent_column,
+    is_phone_column, is_url_column, normalize_fullwidth_digits, parse_sep_directive,
+    ColumnTypeState, EmailRules,
 };
 use crate::error::{CsvAnalyzerError, Result};
-use crate::output::{ErrorResponse, SuccessResponse};
-use crate::types::constants::{BUFF_SIZE, MAX_BYTES, MAX_COLUMNS};
-use crate::types::{ContactProperty, CsvErrorType, DataType};
+use crate::output::{csv::to_csv, DialectResponse, Emit, ErrorResponse, SuccessResponse};
+use crate::stats;
+use crate::types::constants::{BUFF_SIZE, ERROR_CONTEXT_LINES, MAX_BYTES};
+use crate::types::{
+    ColumnStats, ContactProperty, CsvErrorType, DataType, DateTimeGranularity, DetectionReport,
+    DuplicateRows, NumberFormat, StageTimings, Warning, WarningKind,
+};
 use crate::validation::{
-    check_duplicate_fields, is_binary_data, is_valid_string_size, validate_columns_count,
+    check_duplicate_fields, describe_bucket, disallowed_header_char, find_binary_offset,
+    find_duplicate_rows, find_mixed_line_ending, has_trailing_empty_column, is_binary_data,
+    is_valid_string_size, reconcile_trailing_column, truncate_to_size, validate_columns_count,
 };
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::iter;
+use std::time::Instant;
 
 /// CSV Analyzer main struct
 pub struct CsvAnalyzer {
@@ -20,12 +39,32 @@ pub struct CsvAnalyzer {
     text_sep: char,
     field_delim: char,
     skip_header: bool,
+    /// Whether a units row follows the header
+    units_row: bool,
+    skip_lines: usize,
     charset: String,
+    datetime_format: Option<String>,
     current_row: usize,
     current_col: usize,
     current_field: String,
     current_data_type: DataType,
     current_col_count: usize,
+    /// File content supplied in memory, read instead of `config.filename`
+    input: Option<Vec<u8>>,
+    /// Scanned lines of the sample, to quote the offending line in errors
+    source_lines: Vec<String>,
+    /// Line number (1-based) in the file of each scanned line
+    line_numbers: Vec<usize>,
+    /// Contact properties supplied by the caller, queried from the database when unset
+    properties: Option<Vec<ContactProperty>>,
+    /// Parsed rows of the sample, header included
+    rows: Vec<Vec<String>>,
+    /// Columns holding Excel `="..."` text values, typed String
+    excel_text_columns: HashSet<usize>,
+    /// Stage timings of the last analysis
+    timings: StageTimings,
+    /// Byte offset in the file of the data that failed the sample checks
+    error_offset: Option<usize>,
 }
 
 impl CsvAnalyzer {
@@ -35,77 +74,306 @@ impl CsvAnalyzer {
             text_sep: '\0',
             field_delim: '\0',
             skip_header: false,
+            units_row: false,
+            skip_lines: 0,
             charset: "UNKNOWN".to_string(),
+            datetime_format: None,
             current_row: 0,
             current_col: 0,
             current_field: String::new(),
             current_data_type: DataType::String,
             current_col_count: 0,
+            input: None,
+            source_lines: Vec::new(),
+            line_numbers: Vec::new(),
+            properties: None,
+            rows: Vec::new(),
+            excel_text_columns: HashSet::new(),
+            timings: StageTimings::default(),
+            error_offset: None,
+        }
+    }
+
+    /// Create an analyzer using already fetched contact properties instead of
+    /// querying the database, e.g. for a batch of files of the same account
+    pub fn with_properties(config: Config, properties: Vec<ContactProperty>) -> Self {
+        CsvAnalyzer {
+            properties: Some(properties),
+            ..CsvAnalyzer::new(config)
         }
     }
 
     /// Run the analysis and return JSON result
     pub fn analyze(&mut self) -> String {
+        let output_format = self.config.output_format;
+        let emit = self.config.emit;
         match self.analyze_internal() {
-            Ok(response) => response.to_json(),
+            Ok(response) if emit == Emit::Json => response.render(output_format),
+            Ok(_) => self.sample_csv(),
             Err(e) => self.build_error_response(e).to_json(),
         }
     }
 
-    /// Internal analysis implementation
-    fn analyze_internal(&mut self) -> Result<SuccessResponse<'_>> {
-        // Read sample from file
-        let sample = self.read_sample()?;
+    /// Run the analysis like `analyze`, writing the success or error response
+    /// as MessagePack. `output_format` and `emit` don't apply.
+    pub fn analyze_msgpack<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+        let written = match self.analyze_internal() {
+            Ok(response) => response.write_msgpack(writer),
+            Err(e) => self.build_error_response(e).write_msgpack(writer),
+        };
+        written.map_err(|e| CsvAnalyzerError::IoError(io::Error::other(e)))
+    }
 
-        // Check for binary file
-        if is_binary_data(&sample) {
-            return Err(CsvAnalyzerError::CsvError(CsvErrorType::Binary));
+    /// Run the analysis on in-memory content instead of `config.filename`,
+    /// with the same sample limits as a file
+    pub fn analyze_bytes(&mut self, data: &[u8]) -> String {
+        self.input = Some(data.to_vec());
+        self.analyze()
+    }
+
+    /// Run the analysis on base64 encoded content, see `analyze_bytes`
+    pub fn analyze_base64(&mut self, data: &str) -> String {
+        match STANDARD.decode(data.trim()) {
+            Ok(bytes) => self.analyze_bytes(&bytes),
+            Err(e) => self
+                .build_error_response(CsvAnalyzerError::EncodingError(format!(
+                    "Invalid base64 data: {}",
+                    e
+                )))
+                .to_json(),
         }
+    }
+
+    /// Run only the detection stages on `sample` (charset, quote, delimiter,
+    /// header and column count), with the configured overrides. No contact
+    /// properties are needed.
+    pub fn detect(&mut self, sample: &[u8]) -> Result<DetectionReport> {
+        self.timings = StageTimings::default();
+        self.detect_sample(sample, &mut Vec::new())?;
+        Ok(DetectionReport {
+            charset: self.charset.clone(),
+            text_delimiter: self.text_sep,
+            field_delimiter: self.field_delim,
+            has_header: self.skip_header,
+            column_count: self.current_col_count,
+        })
+    }
 
-        // Detect charset
-        self.charset = detect_charset(&sample);
+    /// Read the sample like `analyze` and run only the detection stages,
+    /// returning the dialect (charset, delimiters, header flag) as JSON.
+    /// Neither types nor contact properties are looked at.
+    pub fn detect_only(&mut self) -> String {
+        let detected = self.read_sample().and_then(|sample| self.detect(&sample));
+        match detected {
+            Ok(report) => DialectResponse::new(&report).to_json(),
+            Err(e) => self.build_error_response(e).to_json(),
+        }
+    }
 
-        // Convert to UTF-8
-        let text =
-            convert_to_utf8(&sample, &self.charset).map_err(CsvAnalyzerError::EncodingError)?;
+    /// Charset the analysis would detect for `data`, without analyzing it:
+    /// BOM, then ASCII check, then statistical detection over the sample
+    /// size read by `analyze`. Returns the normalized name
+    /// (e.g. `utf8`, `UTF-8BOM`, `ansi`).
+    pub fn detect_charset_only(data: &[u8]) -> String {
+        detect_charset(&data[..data.len().min(MAX_BYTES)])
+    }
 
-        // Split into lines
-        let lines: Vec<&str> = text.lines().take(self.config.scan_lines + 1).collect();
+    /// Parsed sample of the last analysis as RFC 4180 CSV, with the detected
+    /// delimiter and quote (comma and double quote when there are none)
+    pub fn sample_csv(&self) -> String {
+        let delimiter = if self.field_delim == '\0' {
+            ','
+        } else {
+            self.field_delim
+        };
+        let quote = if self.text_sep == '\0' {
+            '"'
+        } else {
+            self.text_sep
+        };
+        to_csv(&self.rows, delimiter, quote)
+    }
 
-        if lines.is_empty() {
-            return Err(CsvAnalyzerError::CsvError(CsvErrorType::Sample));
+    /// Format parameters detected (or applied) by the last analysis
+    pub fn parse_profile(&self) -> ParseProfile {
+        ParseProfile {
+            charset: self.charset.clone(),
+            field_delimiter: self.field_delim,
+            text_delimiter: self.text_sep,
+            skip_header: self.skip_header,
+            date_time_format: self.datetime_format.clone(),
+            skip_lines: self.skip_lines,
         }
+    }
 
-        // Detect CSV format
-        self.text_sep = detect_quote_char(&lines).unwrap_or('"');
-        self.field_delim = detect_delimiter(&lines, Some(self.text_sep)).unwrap_or('\0');
-        self.skip_header = has_header(&lines, self.text_sep, self.field_delim);
+    /// Time spent in each stage of the last analysis,
+    /// when `config.profile_timings` is set
+    pub fn timings(&self) -> Option<&StageTimings> {
+        self.config.profile_timings.then_some(&self.timings)
+    }
 
-        // Validate column counts
-        let validation = validate_columns_count(&lines, self.field_delim, self.text_sep)?;
-        self.current_col_count = validation.columns_count;
+    /// Run the analysis on rows already split into fields by the caller,
+    /// e.g. with the `csv` crate: no sampling, charset or delimiter
+    /// detection. `headers` are the column names, detected from the first
+    /// row (unless forced) when None. Returns JSON like `analyze`.
+    pub fn analyze_rows(&mut self, headers: Option<Vec<String>>, rows: Vec<Vec<String>>) -> String {
+        let output_format = self.config.output_format;
+        let emit = self.config.emit;
+        match self.analyze_rows_internal(headers, rows) {
+            Ok(response) if emit == Emit::Json => response.render(output_format),
+            Ok(_) => self.sample_csv(),
+            Err(e) => self.build_error_response(e).to_json(),
+        }
+    }
 
-        // Check max columns
-        if self.current_col_count > MAX_COLUMNS {
+    fn analyze_rows_internal(
+        &mut self,
+        headers: Option<Vec<String>>,
+        mut rows: Vec<Vec<String>>,
+    ) -> Result<SuccessResponse<'_>> {
+        self.check_account()?;
+        self.timings = StageTimings::default();
+        self.error_offset = None;
+        self.source_lines.clear();
+        self.line_numbers.clear();
+        self.excel_text_columns.clear();
+        self.charset = "utf8".to_string();
+        self.text_sep = '\0';
+        self.field_delim = '\0';
+        self.skip_lines = 0;
+
+        self.skip_header = match headers {
+            Some(headers) => {
+                rows.insert(0, headers);
+                true
+            }
+            None => self
+                .config
+                .force_header
+                .unwrap_or_else(|| has_header(&rows)),
+        };
+        let first = rows
+            .first()
+            .ok_or(CsvAnalyzerError::CsvError(CsvErrorType::Sample))?;
+        self.current_col_count = match self.config.expected_columns {
+            Some(0) => {
+                return Err(CsvAnalyzerError::ConfigError(
+                    "Expected column count must be positive".to_string(),
+                ))
+            }
+            Some(columns) => columns,
+            None => first.len(),
+        };
+        if self.current_col_count > self.config.max_columns {
             return Err(CsvAnalyzerError::CsvError(CsvErrorType::TooMuchColumns));
         }
 
-        // Parse CSV into rows
-        let rows = self.parse_csv(&lines)?;
+        self.analyze_detected(rows, Vec::new(), None, false)
+    }
+
+    /// The account id is only used to query contact properties
+    fn check_account(&self) -> Result<()> {
+        if self.properties.is_none() && self.config.akid <= 0 {
+            return Err(CsvAnalyzerError::ConfigError(format!(
+                "Invalid account id {}, must be positive",
+                self.config.akid
+            )));
+        }
+        Ok(())
+    }
+
+    /// Internal analysis implementation
+    fn analyze_internal(&mut self) -> Result<SuccessResponse<'_>> {
+        self.check_account()?;
+        self.timings = StageTimings::default();
+
+        // Read sample from file
+        let start = Instant::now();
+        let sample = self.read_sample()?;
+        self.timings.sample_read = Some(start.elapsed());
+        let estimated_lines = if self.config.estimate_rows {
+            self.estimate_line_count(&sample)?
+        } else {
+            None
+        };
+        debug_trace!("Read {} bytes of sample", sample.len());
+
+        let mut warnings: Vec<Warning> = Vec::new();
+        let rows = self.detect_sample(&sample, &mut warnings)?;
+        let full_scan = self.config.full_scan;
+        self.analyze_detected(rows, warnings, estimated_lines, full_scan)
+    }
 
-        // Get headers
-        let headers = if self.skip_header && !rows.is_empty() {
+    /// Analyze the parsed rows of a detected format: headers, email column,
+    /// types and property matching, values. `full_scan` streams the whole
+    /// file through type detection.
+    fn analyze_detected(
+        &mut self,
+        rows: Vec<Vec<String>>,
+        mut warnings: Vec<Warning>,
+        estimated_lines: Option<usize>,
+        full_scan: bool,
+    ) -> Result<SuccessResponse<'_>> {
+        // Get headers, from the file or provided for a headerless one
+        let provided_headers = if self.skip_header {
+            None
+        } else {
+            self.config.provided_headers.clone()
+        };
+        let named_headers = self.skip_header || provided_headers.is_some();
+        let mut headers = if self.skip_header && !rows.is_empty() {
             rows[0].clone()
+        } else if let Some(provided) = provided_headers {
+            if provided.len() != self.current_col_count {
+                return Err(CsvAnalyzerError::ConfigError(format!(
+                    "{} headers provided for {} columns",
+                    provided.len(),
+                    self.current_col_count
+                )));
+            }
+            provided
         } else {
             (1..=self.current_col_count)
                 .map(|i| format!("Field{}", i))
                 .collect()
         };
 
-        // Validate headers
+        // Validate headers, long ones are truncated only after the duplicate check
+        let mut long_headers: Vec<usize> = Vec::new();
         for (i, header) in headers.iter().enumerate() {
             self.current_col = i + 1;
-            if !is_valid_string_size(header) {
+            // Characters are checked on the whole header, before truncation
+            if let Some(c) = self
+                .config
+                .header_chars
+                .as_deref()
+                .and_then(|allowed| disallowed_header_char(header, allowed))
+            {
+                debug_trace!(
+                    "Column {}: {:?} not allowed in header {:?}",
+                    i + 1,
+                    c,
+                    header
+                );
+                self.current_row = if self.skip_header {
+                    self.line_number(0)
+                } else {
+                    0
+                };
+                self.current_field = header.clone();
+                return Err(CsvAnalyzerError::CsvError(CsvErrorType::HeaderChar));
+            }
+            if !is_valid_string_size(header, self.config.max_string_size) {
+                if self.config.collect_warnings {
+                    warnings.push(Warning {
+                        row: self.line_number(0),
+                        col: self.current_col,
+                        kind: WarningKind::ColumnLong,
+                    });
+                    long_headers.push(i);
+                    continue;
+                }
+                self.current_row = self.line_number(0);
                 self.current_field = header.clone();
                 return Err(CsvAnalyzerError::CsvError(CsvErrorType::ColumnLong));
             }
@@ -113,34 +381,86 @@ impl CsvAnalyzer {
 
         // Check for duplicate headers
         check_duplicate_fields(&headers)?;
+        // Long names sharing a prefix are kept apart by a `~N` suffix
+        let max_size = self.config.max_string_size;
+        for i in long_headers {
+            let mut truncated = truncate_to_size(&headers[i], max_size);
+            let mut n = 2;
+            while headers
+                .iter()
+                .enumerate()
+                .any(|(j, h)| j != i && h.to_lowercase() == truncated.to_lowercase())
+            {
+                let suffix = format!("~{}", n);
+                truncated =
+                    truncate_to_size(&headers[i], max_size.saturating_sub(suffix.len())) + &suffix;
+                n += 1;
+            }
+            headers[i] = truncated;
+        }
 
-        // Data rows (skip header if present)
-        let data_rows: Vec<Vec<String>> = if self.skip_header && rows.len() > 1 {
-            rows[1..].to_vec()
+        // Units row after the header, when requested
+        self.units_row = self.config.detect_units_row && self.skip_header && has_units_row(&rows);
+        debug_trace!("Units row: {}", self.units_row);
+
+        // Data rows (skip header and units row if present)
+        let data_rows: Vec<Vec<String>> = if self.skip_header {
+            rows[(1 + usize::from(self.units_row)).min(rows.len())..].to_vec()
         } else {
             rows.clone()
         };
 
         // Detect email column
         let header_ref: Vec<String> = headers.clone();
+        let email_rules = EmailRules {
+            regex: self.config.email_regex.as_ref(),
+            allowed_tlds: self.config.allowed_tlds.as_deref(),
+        };
         let email_col = detect_email_column(
             &data_rows,
-            if self.skip_header {
+            if named_headers {
                 Some(&header_ref)
             } else {
                 None
             },
-            self.skip_header,
-        )
-        .ok_or(CsvAnalyzerError::CsvError(CsvErrorType::EmailNotFound))?;
+            named_headers,
+            &email_rules,
+        );
+        debug_trace!("Email column: {:?}", email_col);
+        if email_col.is_none() && self.config.require_email {
+            return Err(CsvAnalyzerError::CsvError(CsvErrorType::EmailNotFound));
+        }
 
         // Connect to database and get contact properties
+        let start = Instant::now();
         let properties = self.get_contact_properties()?;
+        self.timings.db = Some(start.elapsed());
+        debug_trace!("{} contact properties", properties.len());
+
+        // Stream the whole file through type detection when requested
+        let start = Instant::now();
+        let mut scanned_types = if full_scan && self.config.infer_types {
+            Some(self.scan_column_types()?)
+        } else {
+            None
+        };
 
         // Detect data types and match field names
         let mut field_names: Vec<String> = Vec::new();
         let mut data_types: Vec<DataType> = Vec::new();
+        let mut null_counts: Vec<usize> = Vec::new();
+        let mut column_stats: Vec<ColumnStats> = Vec::new();
+        let mut phone_named = false;
+        let mut number_formats: Vec<Option<NumberFormat>> = Vec::new();
+        let mut granularities: Vec<Option<DateTimeGranularity>> = Vec::new();
+        let mut currency_symbols: Vec<Option<String>> = Vec::new();
+        let mut semantic_types: Vec<Option<String>> = Vec::new();
+        let mut match_notes: Vec<String> = Vec::new();
+        let assigned = self.assign_properties(&headers, &properties, &mut match_notes);
         let mut datetime_format: Option<String> = None;
+        let mut date_patterns = None;
+        // Plain Float columns using each decimal separator
+        let (mut dot_columns, mut comma_columns) = (0, 0);
 
         for (col_idx, header) in headers.iter().enumerate() {
             self.current_col = col_idx + 1;
@@ -150,95 +470,589 @@ impl CsvAnalyzer {
                 .iter()
                 .filter_map(|row| row.get(col_idx).map(|s| s.as_str()))
                 .collect();
+            let normalized: Vec<String>;
+            let col_values = if self.config.normalize_fullwidth {
+                normalized = col_values
+                    .iter()
+                    .map(|v| normalize_fullwidth_digits(v))
+                    .collect();
+                normalized.iter().map(String::as_str).collect()
+            } else {
+                col_values
+            };
 
             // Match property
-            let matched_prop = match_property(header, &properties);
+            let matched_prop = assigned[col_idx].map(|(prop, fuzzy)| {
+                if let Some(distance) = fuzzy {
+                    match_notes.push(format!(
+                        "header '{}' fuzzy-matched property '{}' (distance {})",
+                        header, prop.name, distance
+                    ));
+                }
+                prop
+            });
             let meta_type = matched_prop.map(|p| p.datatype);
 
-            // Detect data type
-            let (detected_type, patterns) = detect_data_type(&col_values, meta_type);
+            // Custom detectors claim a column before the built-in detection
+            let semantic_type = self
+                .config
+                .infer_types
+                .then(|| detect_semantic_type(&col_values, &self.config.type_detectors))
+                .flatten();
+
+            // Detect data type, percentages and amounts are reported as Float
+            let currency = (self.config.infer_types && semantic_type.is_none())
+                .then(|| detect_currency_column(&col_values))
+                .flatten();
+            currency_symbols.push(currency.map(String::from));
+            let (detected_type, patterns) = if !self.config.infer_types
+                || semantic_type.is_some()
+                || self.excel_text_columns.contains(&col_idx)
+            {
+                // Excel quotes values to keep them as text, e.g. leading zeros
+                number_formats.push(None);
+                (DataType::String, None)
+            } else if is_percent_column(&col_values) {
+                number_formats.push(Some(NumberFormat::Percent));
+                (DataType::Float, None)
+            } else if currency.is_some() {
+                number_formats.push(Some(NumberFormat::Currency));
+                (DataType::Float, None)
+            } else {
+                number_formats.push(None);
+                match scanned_types {
+                    Some(ref mut states) => std::mem::take(&mut states[col_idx]).finish(meta_type),
+                    None => detect_data_type(&col_values, meta_type),
+                }
+            };
+            debug_trace!(
+                "Column {} '{}': {:?}, number format {:?}, property {:?}",
+                col_idx + 1,
+                header,
+                detected_type,
+                number_formats.last().copied().flatten(),
+                matched_prop.map(|p| &p.name)
+            );
             data_types.push(detected_type);
+            if detected_type == DataType::Float && number_formats.last() == Some(&None) {
+                match detect_decimal_separator(&col_values) {
+                    Some('.') => dot_columns += 1,
+                    Some(',') => comma_columns += 1,
+                    _ => {}
+                }
+            }
+
+            // Blank cells, short rows lack the trailing ones
+            let blanks = col_values.iter().filter(|v| v.trim().is_empty()).count();
+            null_counts.push(blanks + data_rows.len() - col_values.len());
+            if self.config.compute_stats {
+                column_stats.push(stats::column_stats(&col_values, detected_type));
+            }
+
+            granularities.push(
+                patterns
+                    .as_ref()
+                    .filter(|_| detected_type == DataType::DateTime)
+                    .and_then(|p| p.granularity()),
+            );
 
             // Track datetime format
             if detected_type == DataType::DateTime {
-                if let Some(ref p) = patterns {
+                if let Some(mut p) = patterns {
+                    if let Some(order) = self.config.prefer_date_order {
+                        p.prefer_date_order(order);
+                    }
                     if let Some(fmt) = p.format_string() {
                         if datetime_format.is_none()
                             || datetime_format.as_ref().map(|f| f.len()).unwrap_or(0) < fmt.len()
                         {
                             datetime_format = Some(fmt);
+                            date_patterns = Some(p);
                         }
                     }
                 }
             }
 
-            // Determine field name
-            if col_idx == email_col {
-                field_names.push("email".to_string());
-            } else if let Some(prop) = matched_prop {
-                // Only use property name if types match
-                if detected_type == prop.datatype {
-                    field_names.push(prop.name.clone());
-                } else {
-                    field_names.push(String::new());
+            // Determine field name, only use property name if types match.
+            // For the email column it must be configured to take precedence.
+            let property_name = matched_prop
+                .filter(|prop| detected_type == prop.datatype)
+                .map(|prop| prop.name.clone());
+            if let Some(prop) = matched_prop.filter(|prop| detected_type != prop.datatype) {
+                match_notes.push(format!(
+                    "header '{}' matched property '{}' but detected {} vs expected {}",
+                    header,
+                    prop.name,
+                    detected_type.name(),
+                    prop.datatype.name()
+                ));
+            }
+            if Some(col_idx) == email_col {
+                match property_name {
+                    Some(name) if self.config.email_property_precedence => field_names.push(name),
+                    _ => field_names.push("email".to_string()),
                 }
+            } else if let Some(name) = property_name {
+                field_names.push(name);
+            } else if !phone_named
+                && semantic_type.is_none()
+                && detected_type == DataType::String
+                && is_phone_column(&col_values)
+            {
+                // Like email, the first phone column gets a fixed name
+                debug_trace!("Column {}: phone numbers", col_idx + 1);
+                field_names.push("phone".to_string());
+                phone_named = true;
             } else {
                 field_names.push(String::new());
             }
+            semantic_types.push(semantic_type);
+        }
+        self.timings.type_detection = Some(start.elapsed());
+
+        // Check columns against the expected types
+        for &(col, expected) in &self.config.expected_types {
+            let detected = match col.checked_sub(1).and_then(|idx| data_types.get(idx)) {
+                Some(&detected) => detected,
+                None => {
+                    return Err(CsvAnalyzerError::ConfigError(format!(
+                        "Expected type for column {} out of {} columns",
+                        col,
+                        data_types.len()
+                    )))
+                }
+            };
+            if detected == expected {
+                continue;
+            }
+            debug_trace!(
+                "Column {}: expected {:?}, detected {:?}",
+                col,
+                expected,
+                detected
+            );
+            if self.config.collect_warnings {
+                warnings.push(Warning {
+                    row: 0,
+                    col,
+                    kind: WarningKind::TypeMismatch,
+                });
+                continue;
+            }
+            self.current_row = 0;
+            self.current_col = col;
+            self.current_field = headers[col - 1].clone();
+            self.current_data_type = detected;
+            return Err(CsvAnalyzerError::CsvError(CsvErrorType::TypeMismatch));
         }
 
-        // Validate field values and prepare data for output
+        // Validate field values of every row, whatever the rows returned,
+        // and prepare data for output
         let mut output_data: Vec<Vec<String>> = Vec::new();
+        let mut missing_cells: Vec<Vec<bool>> = Vec::new();
+        let returned_rows = self.config.return_offset
+            ..self
+                .config
+                .return_offset
+                .saturating_add(self.config.return_lines);
         for (row_idx, row) in data_rows.iter().enumerate() {
-            if output_data.len() >= self.config.return_lines {
-                break;
+            let returned = returned_rows.contains(&row_idx);
+            self.current_row = self.line_number(self.header_lines() + row_idx);
+            if returned && self.config.null_for_missing {
+                // Rows are parsed one per source line, none for rows split
+                // by the caller
+                let quoted = self
+                    .source_lines
+                    .get(self.header_lines() + row_idx)
+                    .map(|line| self.parse_line_quoting(line).quoted)
+                    .unwrap_or_default();
+                // Padding added for `expected_columns` is missing too
+                missing_cells.push(
+                    row.iter()
+                        .zip(quoted.into_iter().chain(iter::repeat(false)))
+                        .map(|(value, quoted)| value.is_empty() && !quoted)
+                        .collect(),
+                );
             }
 
-            self.current_row = if self.skip_header {
-                row_idx + 2
-            } else {
-                row_idx + 1
-            };
-
             let mut output_row: Vec<String> = Vec::new();
             for (col_idx, value) in row.iter().enumerate() {
                 self.current_col = col_idx + 1;
 
-                if !is_valid_string_size(value) {
+                if !is_valid_string_size(value, self.config.max_string_size) {
+                    if self.config.collect_warnings {
+                        warnings.push(Warning {
+                            row: self.current_row,
+                            col: self.current_col,
+                            kind: WarningKind::ValueLong,
+                        });
+                        output_row.push(truncate_to_size(value, self.config.max_string_size));
+                        continue;
+                    }
                     self.current_field = value.clone();
                     return Err(CsvAnalyzerError::CsvError(CsvErrorType::ValueLong));
                 }
 
                 output_row.push(value.clone());
             }
-            output_data.push(output_row);
+            if returned {
+                output_data.push(output_row);
+            }
+        }
+
+        if let Some(fmt) = self
+            .config
+            .parse_profile
+            .as_ref()
+            .and_then(|p| p.date_time_format.clone())
+        {
+            datetime_format = Some(fmt);
         }
+        self.datetime_format = datetime_format.clone();
+        let units_row = self.units_row.then(|| rows[1].clone());
+        self.rows = rows;
 
         // Build success response
         let mut response = SuccessResponse::new(&self.config.locale, &self.charset);
         response.skip_header = self.skip_header;
+        let decimal_separator = match (dot_columns, comma_columns) {
+            (0, 0) => None,
+            _ if comma_columns > dot_columns => Some(','),
+            _ => Some('.'),
+        };
+        response.suggested_locale =
+            guess_locale(date_patterns.as_ref(), decimal_separator).map(String::from);
+        if self.config.report_charset_label {
+            response.charset_label = Some(charset_label(&self.charset));
+        }
         response.set_field_separator(self.field_delim);
         response.set_text_delimiter(self.text_sep);
         response.date_time_format = datetime_format;
         response.header_names = headers;
         response.field_names = field_names;
+        if self.config.detect_id_column {
+            response.id_column = stats::detect_id_column(&data_rows, &data_types);
+            if let Some(col) = response
+                .id_column
+                .filter(|&col| data_types[col] == DataType::Integer)
+            {
+                let values: Vec<&str> = data_rows.iter().map(|row| row[col].as_str()).collect();
+                response.id_sequence = stats::id_sequence(&values);
+            }
+        }
+        if self.config.detect_url_columns {
+            let url_columns: Vec<usize> = (0..data_types.len())
+                .filter(|&col| data_types[col] == DataType::String)
+                .filter(|&col| {
+                    let values: Vec<&str> = data_rows
+                        .iter()
+                        .filter_map(|row| row.get(col).map(|s| s.as_str()))
+                        .collect();
+                    is_url_column(&values)
+                })
+                .collect();
+            response.url_columns = (!url_columns.is_empty()).then_some(url_columns);
+        }
         response.data_types = data_types;
+        response.null_counts = self.config.report_null_counts.then_some(null_counts);
+        if self.config.compute_stats {
+            response.column_stats = Some(column_stats);
+        }
+        response.warnings = warnings;
+        if self.config.detect_duplicate_rows {
+            response.duplicate_rows =
+                find_duplicate_rows(&data_rows).map(|(count, idx)| DuplicateRows {
+                    count,
+                    first_row: self.line_number(self.header_lines() + idx),
+                });
+        }
+        response.units_row = units_row;
+        response.match_notes = match_notes;
+        response.estimated_rows =
+            estimated_lines.map(|lines| lines.saturating_sub(self.first_data_line()));
+        response.set_number_formats(number_formats);
+        response.set_granularities(granularities);
+        response.set_currency_symbols(currency_symbols);
+        response.set_semantic_types(semantic_types);
         response.data = if output_data.is_empty() {
             None
         } else {
             Some(output_data)
         };
+        if self.config.null_for_missing {
+            response.missing_cells = Some(missing_cells);
+        }
+        response.type_names = self.config.type_names_as_strings;
 
         Ok(response)
     }
 
-    /// Read sample data from file
-    fn read_sample(&self) -> Result<Vec<u8>> {
-        let file = File::open(&self.config.filename)?;
-        let mut reader = BufReader::new(file);
+    /// Run the detection stages on a sample: charset, quote, delimiter,
+    /// header and column count, unless forced or replayed from a profile.
+    /// Sets the detected format and source lines, returns the parsed rows.
+    fn detect_sample(
+        &mut self,
+        sample: &[u8],
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vec<Vec<String>>> {
+        self.error_offset = None;
+        self.source_lines.clear();
+        self.line_numbers.clear();
+
+        let profile = self.config.parse_profile.clone();
+
+        // Detect charset, unless forced or replayed from a profile
+        let start = Instant::now();
+        let (charset, source) = self.sample_charset(sample);
+        self.charset = charset;
+        self.timings.charset = Some(start.elapsed());
+        debug_trace!("Charset: {} ({})", self.charset, source);
+
+        // Check for binary file, on the decoded text for UTF-16 where every
+        // other byte of Latin text is NUL
+        let utf16 = is_utf16(&self.charset);
+        if !utf16 {
+            if let Some(offset) = find_binary_offset(sample) {
+                self.error_offset = Some(offset);
+                return Err(CsvAnalyzerError::CsvError(CsvErrorType::Binary));
+            }
+        }
+
+        // Convert to UTF-8
+        let max_error_ratio = if self.config.strict_encoding {
+            Some(0.0)
+        } else {
+            self.config.max_decode_error_ratio
+        };
+        // Only exceeding the tolerated decode errors is an Encoding error
+        let text = convert_to_utf8_max_errors(sample, &self.charset, max_error_ratio).map_err(
+            |e| match e {
+                ConvertError::InvalidUtf8(msg) => CsvAnalyzerError::EncodingError(msg),
+                ConvertError::TooManyErrors(msg) => {
+                    CsvAnalyzerError::CsvErrorDetail(CsvErrorType::Encoding, msg)
+                }
+            },
+        )?;
+        let text = if self.config.strip_nul_bytes {
+            let (text, nul_lines) = strip_nul_chars(text);
+            warnings.extend(nul_lines.into_iter().map(|row| Warning {
+                row,
+                col: 0,
+                kind: WarningKind::NulBytes,
+            }));
+            text
+        } else {
+            text
+        };
+        if utf16 && is_binary_data(text.as_bytes()) {
+            return Err(CsvAnalyzerError::CsvError(CsvErrorType::Binary));
+        }
+
+        // Split into lines
+        self.skip_lines = profile
+            .as_ref()
+            .map_or(self.config.skip_lines, |p| p.skip_lines);
+        let mut lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .skip(self.skip_lines)
+            .take(self.config.scan_lines + 1)
+            .map(|(i, line)| (i + 1, line))
+            .collect();
+
+        // Control characters (e.g. a \x1A EOF marker) appended to the lines
+        // would stick to the last field
+        if self.config.strip_trailing_control {
+            for (number, line) in lines.iter_mut() {
+                let stripped = trim_trailing_control(line);
+                if stripped.len() < line.len() {
+                    warnings.push(Warning {
+                        row: *number,
+                        col: 0,
+                        kind: WarningKind::ControlChars,
+                    });
+                    *line = stripped;
+                }
+            }
+        }
+
+        // An Excel `sep=X` line declares the delimiter, it isn't part of the data
+        let declared_delim = if profile.is_none() {
+            lines.first().and_then(|&(_, l)| parse_sep_directive(l))
+        } else {
+            None
+        };
+        if let Some(delim) = declared_delim {
+            debug_trace!("Delimiter {:?} declared by a sep= line", delim);
+            lines.remove(0);
+            self.skip_lines += 1;
+        }
+
+        // Comment lines are left out, errors still report file line numbers
+        if let Some(prefix) = self.config.comment_prefix {
+            lines.retain(|&(_, l)| !is_comment_line(l, prefix));
+        }
+
+        // Whitespace-only lines after the last data row would otherwise
+        // count as single-field rows
+        let line_count = lines.len();
+        while lines.last().is_some_and(|(_, l)| l.trim().is_empty()) {
+            lines.pop();
+        }
+        if lines.len() < line_count {
+            debug_trace!("Dropped {} trailing blank lines", line_count - lines.len());
+        }
+
+        if lines.is_empty() {
+            self.error_offset = Some(sample.len());
+            return Err(CsvAnalyzerError::CsvError(CsvErrorType::Sample));
+        }
+        let (line_numbers, lines): (Vec<usize>, Vec<&str>) = lines.into_iter().unzip();
+        self.line_numbers = line_numbers;
+        self.source_lines = lines.iter().map(|l| l.to_string()).collect();
+
+        // Detect CSV format
+        if let Some(ref p) = profile {
+            self.text_sep = p.text_delimiter;
+            self.field_delim = p.field_delimiter;
+            self.skip_header = p.skip_header;
+        } else {
+            let start = Instant::now();
+            self.text_sep = detect_quote_char(&lines, self.config.text_sep_percent).unwrap_or('"');
+            self.timings.quote = Some(start.elapsed());
+
+            let start = Instant::now();
+            self.field_delim = declared_delim
+                .or_else(|| detect_delimiter(&lines, Some(self.text_sep)))
+                .unwrap_or('\0');
+            self.timings.delimiter = Some(start.elapsed());
+        }
+
+        // Line endings, on the raw bytes where UTF-16 isn't ASCII compatible
+        if !utf16 {
+            let quote = Some(self.text_sep).filter(|&c| c != '\0' && c.is_ascii());
+            if let Some(row) = find_mixed_line_ending(sample, quote.map(|c| c as u8)) {
+                debug_trace!("Line {}: line ending differs from the first line", row);
+                warnings.push(Warning {
+                    row,
+                    col: 0,
+                    kind: WarningKind::MixedLineEndings,
+                });
+            }
+        }
+
+        // Parse CSV into rows
+        let mut rows = self.parse_csv(&lines, warnings)?;
+        let trim_trailing = self.config.trim_trailing_empty_column
+            && self.config.expected_columns.is_none()
+            && has_trailing_empty_column(&rows);
+        if trim_trailing {
+            debug_trace!("Dropping the trailing empty column");
+            for row in rows.iter_mut() {
+                if row.len() > 1 && row.last().is_some_and(|v| v.is_empty()) {
+                    row.pop();
+                }
+            }
+        }
+        // A trailing delimiter on the first line only, or on the others only
+        let off_by_one = !trim_trailing
+            && self.config.expected_columns.is_none()
+            && reconcile_trailing_column(&mut rows);
+        if off_by_one {
+            debug_trace!("Dropping the trailing empty column the first line disagrees on");
+        }
+        if profile.is_none() {
+            self.skip_header = match self.config.force_header {
+                Some(force) => force,
+                None => {
+                    let start = Instant::now();
+                    let detected = has_header(&rows);
+                    self.timings.header = Some(start.elapsed());
+                    detected
+                }
+            };
+        }
+        debug_trace!(
+            "Format over {} lines: text delimiter {:?}, field delimiter {:?}, header {}",
+            lines.len(),
+            self.text_sep,
+            self.field_delim,
+            self.skip_header
+        );
+
+        // Validate column counts, unless given
+        if let Some(columns) = self.config.expected_columns {
+            if columns == 0 {
+                return Err(CsvAnalyzerError::ConfigError(
+                    "Expected column count must be positive".to_string(),
+                ));
+            }
+            self.current_col_count = columns;
+        } else {
+            let start = Instant::now();
+            // The first line was reconciled with the others, whose count is checked
+            let skipped = usize::from(off_by_one);
+            let validation = validate_columns_count(
+                &lines[skipped..],
+                self.field_delim,
+                self.text_sep,
+                self.config.escape_char,
+                self.config.max_columns,
+                self.config.column_count_percent,
+            )?;
+            self.current_col_count = if off_by_one {
+                rows[0].len()
+            } else {
+                validation.columns_count - usize::from(trim_trailing)
+            };
+            self.timings.column_validation = Some(start.elapsed());
+            if self.config.strict_columns && validation.error_row > 0 {
+                self.current_row = self.line_number(skipped + validation.error_row - 1);
+                return Err(CsvAnalyzerError::CsvErrorDetail(
+                    CsvErrorType::VariousFieldsCount,
+                    describe_bucket(&validation.bucket),
+                ));
+            }
+        }
+        debug_trace!("Column count: {}", self.current_col_count);
+
+        // Check max columns
+        if self.current_col_count > self.config.max_columns {
+            return Err(CsvAnalyzerError::CsvError(CsvErrorType::TooMuchColumns));
+        }
+
+        Ok(rows)
+    }
+
+    /// Open the content to analyze, in memory or from file
+    fn open_input(&self) -> Result<Box<dyn Read + '_>> {
+        match self.input {
+            Some(ref data) => Ok(Box::new(data.as_slice())),
+            None => Ok(Box::new(File::open(&self.config.filename)?)),
+        }
+    }
+
+    /// Read sample data from file, the scanned lines after the skipped ones,
+    /// up to `config.max_bytes`. A last line cut by the byte limit is left out.
+    fn read_sample(&mut self) -> Result<Vec<u8>> {
+        let max_bytes = self.config.max_bytes;
+        if max_bytes == 0 {
+            return Err(CsvAnalyzerError::ConfigError(
+                "Sample size must be positive".to_string(),
+            ));
+        }
+        let max_lines = self.config.scan_lines
+            + self
+                .config
+                .parse_profile
+                .as_ref()
+                .map_or(self.config.skip_lines, |p| p.skip_lines);
+        let mut reader = BufReader::new(self.open_input()?);
         let mut sample = Vec::new();
         let mut line_count = 0;
         let mut total_bytes = 0;
+        // Data left after the byte limit was reached
+        let mut cut = false;
 
         loop {
             let mut buffer = vec![0u8; BUFF_SIZE];
@@ -248,7 +1062,7 @@ impl CsvAnalyzer {
                 break;
             }
 
-            for &byte in &buffer[..bytes_read] {
+            for (i, &byte) in buffer[..bytes_read].iter().enumerate() {
                 sample.push(byte);
                 total_bytes += 1;
 
@@ -256,29 +1070,172 @@ impl CsvAnalyzer {
                     line_count += 1;
                 }
 
-                if line_count > self.config.scan_lines || total_bytes >= MAX_BYTES {
+                if line_count > max_lines || total_bytes >= max_bytes {
+                    cut = i + 1 < bytes_read;
                     break;
                 }
             }
 
-            if line_count > self.config.scan_lines || total_bytes >= MAX_BYTES {
+            if line_count > max_lines || total_bytes >= max_bytes {
+                cut = cut || !reader.fill_buf()?.is_empty();
                 break;
             }
         }
 
+        // Only complete records go to detection, cut on the encoded line
+        // feed: a UTF-16 one is two bytes
+        if cut {
+            let (charset, _) = self.sample_charset(&sample);
+            if let Some(end) = last_line_end(&sample, &charset) {
+                debug_trace!("Dropping {} bytes of a cut line", sample.len() - end);
+                sample.truncate(end);
+            }
+        }
+
         if sample.is_empty() || line_count == 0 {
+            // Where the data ran out before a complete line
+            drop(reader);
+            self.error_offset = Some(sample.len());
             return Err(CsvAnalyzerError::CsvError(CsvErrorType::Sample));
         }
 
         Ok(sample)
     }
 
-    /// Parse CSV lines into rows
-    fn parse_csv(&self, lines: &[&str]) -> Result<Vec<Vec<String>>> {
+    /// Charset of the sample and where it comes from: forced, replayed
+    /// from a profile or detected
+    fn sample_charset(&self, sample: &[u8]) -> (String, &'static str) {
+        match (&self.config.force_charset, &self.config.parse_profile) {
+            (Some(charset), _) => (charset.clone(), "forced"),
+            (None, Some(p)) => (p.charset.clone(), "profile"),
+            (None, None) => (detect_charset(sample), "detected"),
+        }
+    }
+
+    /// Estimated number of lines of the analyzed file, None for in-memory content
+    fn estimate_line_count(&self, sample: &[u8]) -> Result<Option<usize>> {
+        if self.input.is_some() {
+            return Ok(None);
+        }
+        let file_size = std::fs::metadata(&self.config.filename)?.len();
+        Ok(Some(stats::estimate_line_count(sample, file_size)))
+    }
+
+    /// Number of lines before the first data row: skipped lines, header and units row
+    fn first_data_line(&self) -> usize {
+        self.skip_lines + self.header_lines()
+    }
+
+    /// Number of scanned lines before the first data row: header and units row
+    fn header_lines(&self) -> usize {
+        usize::from(self.skip_header) + usize::from(self.units_row)
+    }
+
+    /// Line number (1-based) in the file of the scanned line at `index`
+    fn line_number(&self, index: usize) -> usize {
+        self.line_numbers
+            .get(index)
+            .copied()
+            .unwrap_or(self.skip_lines + index + 1)
+    }
+
+    /// Detect column types over every data row of the file, decoding and
+    /// parsing one line at a time
+    fn scan_column_types(&mut self) -> Result<Vec<ColumnTypeState>> {
+        let input = self.open_input()?;
+
+        let mut read_error = None;
+        // Line number (1-based) of the line with too many fields
+        let mut too_many_fields = None;
+        let mut row_count = 0;
+        let comment_prefix = self.config.comment_prefix;
+        let rows = DecodedLines::new(BufReader::new(input), &self.charset)
+            .enumerate()
+            .skip(self.skip_lines)
+            .filter(|(_, line)| match (line, comment_prefix) {
+                (Ok(line), Some(prefix)) => !is_comment_line(line, prefix),
+                _ => true,
+            })
+            .skip(self.header_lines())
+            .map_while(|(idx, line)| match line {
+                Ok(line) if line.trim().is_empty() => Some(Vec::new()),
+                Ok(mut line) => {
+                    row_count += 1;
+                    if self.config.strip_nul_bytes {
+                        line.retain(|c| c != '\0');
+                    }
+                    if self.config.strip_trailing_control {
+                        line.truncate(trim_trailing_control(&line).len());
+                    }
+                    let parsed = self.parse_line_quoting(&line);
+                    if parsed.too_many_fields {
+                        too_many_fields = Some(idx + 1);
+                        return None;
+                    }
+                    let fields = parsed.fields;
+                    Some(if self.config.normalize_fullwidth {
+                        fields
+                            .iter()
+                            .map(|v| normalize_fullwidth_digits(v))
+                            .collect()
+                    } else {
+                        fields
+                    })
+                }
+                Err(e) => {
+                    read_error = Some(e);
+                    None
+                }
+            });
+        let states = detect_column_types(rows, self.current_col_count);
+
+        if let Some(e) = read_error {
+            return Err(e.into());
+        }
+        if let Some(row) = too_many_fields {
+            self.current_row = row;
+            return Err(CsvAnalyzerError::CsvError(CsvErrorType::TooMuchColumns));
+        }
+        debug_trace!("Full scan over {} data rows", row_count);
+        Ok(states)
+    }
+
+    /// Parse CSV lines into rows. With `expected_columns`, shorter rows are
+    /// padded with empty values and longer ones truncated, with a warning.
+    /// Columns with Excel `="..."` text values are recorded.
+    fn parse_csv(
+        &mut self,
+        lines: &[&str],
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vec<Vec<String>>> {
         let mut rows = Vec::new();
+        self.excel_text_columns.clear();
 
-        for line in lines {
-            let fields = self.parse_line(line);
+        for (idx, line) in lines.iter().enumerate() {
+            let parsed = self.parse_line_quoting(line);
+            if parsed.too_many_fields {
+                self.current_row = self.line_number(idx);
+                return Err(CsvAnalyzerError::CsvError(CsvErrorType::TooMuchColumns));
+            }
+            self.excel_text_columns.extend(
+                parsed
+                    .excel_text
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &excel)| excel)
+                    .map(|(col, _)| col),
+            );
+            let mut fields = parsed.fields;
+            if let Some(columns) = self.config.expected_columns {
+                if fields.len() > columns {
+                    warnings.push(Warning {
+                        row: self.line_number(idx),
+                        col: columns + 1,
+                        kind: WarningKind::ExtraColumns,
+                    });
+                }
+                fields.resize(columns, String::new());
+            }
             rows.push(fields);
         }
 
@@ -286,68 +1243,166 @@ impl CsvAnalyzer {
     }
 
     /// Parse a single CSV line into fields
+    #[cfg(test)]
     fn parse_line(&self, line: &str) -> Vec<String> {
+        self.parse_line_quoting(line).fields
+    }
+
+    /// Parse a single CSV line into fields, along with whether each field
+    /// was quoted and whether it was an Excel `="..."` text value. Parsing
+    /// stops at `max_row_fields` fields, flagging the line.
+    fn parse_line_quoting(&self, line: &str) -> ParsedLine {
         if self.field_delim == '\0' {
-            return vec![line.to_string()];
+            return ParsedLine {
+                fields: vec![line.to_string()],
+                quoted: vec![false],
+                excel_text: vec![false],
+                too_many_fields: false,
+            };
         }
 
-        let mut fields = Vec::new();
+        let mut parsed = ParsedLine::default();
         let mut current_field = String::new();
+        let mut current_quoted = false;
+        let mut current_excel = false;
         let mut inside_quotes = false;
-        let chars = line.chars().peekable();
+        let mut chars = line.chars();
 
-        for c in chars {
-            if c == self.text_sep {
+        while let Some(c) = chars.next() {
+            if Some(c) == self.config.escape_char {
+                // Escaped character is taken literally, quoted or not
+                if let Some(escaped) = chars.next() {
+                    current_field.push(escaped);
+                }
+            } else if c == self.text_sep {
+                // A quote right after a leading `=` opens an Excel text value
+                if self.config.unwrap_excel_text && !inside_quotes && current_field.trim() == "=" {
+                    current_excel = true;
+                }
                 inside_quotes = !inside_quotes;
+                current_quoted = true;
             } else if c == self.field_delim && !inside_quotes {
-                fields.push(current_field.trim().to_string());
+                if parsed.fields.len() + 1 >= self.config.max_row_fields {
+                    parsed.too_many_fields = true;
+                    break;
+                }
+                parsed.push(&current_field, current_quoted, current_excel);
                 current_field = String::new();
+                current_quoted = false;
+                current_excel = false;
             } else {
                 current_field.push(c);
             }
         }
 
         // Don't forget the last field
-        fields.push(current_field.trim().to_string());
+        parsed.push(&current_field, current_quoted, current_excel);
 
-        fields
+        parsed
     }
 
-    /// Get contact properties from database
-    fn get_contact_properties(&self) -> Result<Vec<ContactProperty>> {
-        let mut db = DbConnection::new(self.config.db.clone());
+    /// Field name each header would map to (property name, or blank) without
+    /// running detection. The full analysis additionally blanks properties
+    /// whose type differs from the detected one and names the email column.
+    pub fn preview_field_names(&self, headers: &[String]) -> Result<Vec<String>> {
+        let properties = self.get_contact_properties()?;
+        Ok(self
+            .assign_properties(headers, &properties, &mut Vec::new())
+            .into_iter()
+            .map(|matched| matched.map_or_else(String::new, |(p, _)| p.name.clone()))
+            .collect())
+    }
 
-        // Try to connect to database
-        match db.connect_global() {
-            Ok(_) => {}
-            Err(e) => {
-                // Database not available, return empty properties
-                eprintln!("Warning: Database not available: {}", e);
-                return Ok(Vec::new());
+    /// Property of each header, each property given to one column only:
+    /// exact matches first, then normalized ones, then the closest fuzzy
+    /// ones, earlier columns winning ties. Headers whose property is taken
+    /// are noted in `match_notes`.
+    fn assign_properties<'p>(
+        &self,
+        headers: &[String],
+        properties: &'p [ContactProperty],
+        match_notes: &mut Vec<String>,
+    ) -> Vec<Option<(&'p ContactProperty, Option<usize>)>> {
+        let matches: Vec<_> = headers
+            .iter()
+            .map(|header| self.find_property(header, properties))
+            .collect();
+        let mut order: Vec<(usize, usize)> = matches
+            .iter()
+            .enumerate()
+            .filter_map(|(col, matched)| {
+                let (prop, fuzzy) = (*matched)?;
+                let exact = prop.name.to_lowercase() == headers[col].to_lowercase();
+                Some((fuzzy.map_or(usize::from(!exact), |d| 2 + d), col))
+            })
+            .collect();
+        order.sort_unstable();
+
+        let mut assigned = vec![None; headers.len()];
+        let mut taken: HashMap<&str, usize> = HashMap::new();
+        let mut notes = Vec::new();
+        for (_, col) in order {
+            let Some((prop, fuzzy)) = matches[col] else {
+                continue;
+            };
+            match taken.get(prop.name.as_str()) {
+                Some(&owner) => notes.push((
+                    col,
+                    format!(
+                        "header '{}' matched property '{}' already mapped to column {}",
+                        headers[col],
+                        prop.name,
+                        owner + 1
+                    ),
+                )),
+                None => {
+                    taken.insert(&prop.name, col);
+                    assigned[col] = Some((prop, fuzzy));
+                }
             }
         }
+        notes.sort_unstable();
+        match_notes.extend(notes.into_iter().map(|(_, note)| note));
+        assigned
+    }
 
-        // Get pool info
-        let pool_info = match db.get_pool_info(self.config.akid) {
-            Ok(info) => info,
-            Err(e) => {
-                eprintln!("Warning: Could not get pool info: {}", e);
-                return Ok(Vec::new());
-            }
-        };
+    /// Property a header maps to, with the edit distance when it was only
+    /// found by the fuzzy fallback
+    fn find_property<'p>(
+        &self,
+        header: &str,
+        properties: &'p [ContactProperty],
+    ) -> Option<(&'p ContactProperty, Option<usize>)> {
+        match_property(header, properties)
+            .map(|prop| (prop, None))
+            .or_else(|| {
+                let max_distance = self.config.fuzzy_match_distance?;
+                fuzzy_match_property(header, properties, max_distance)
+                    .map(|(prop, distance)| (prop, Some(distance)))
+            })
+    }
 
-        // Connect to user pool
-        if let Err(e) = db.connect_user_pool(&pool_info) {
-            eprintln!("Warning: Could not connect to user pool: {}", e);
-            return Ok(Vec::new());
+    /// Get contact properties, from the database unless supplied
+    fn get_contact_properties(&self) -> Result<Vec<ContactProperty>> {
+        match self.properties {
+            Some(ref props) => Ok(props.clone()),
+            None => Ok(Self::fetch_contact_properties(&self.config)),
         }
+    }
 
-        // Get contact properties
-        match db.get_contact_properties(self.config.akid) {
-            Ok(props) => Ok(props),
+    /// Fetch the contact properties of `config.akid` from the database.
+    /// Database failures are reported on stderr, unless quiet, and yield no
+    /// properties.
+    pub fn fetch_contact_properties(config: &Config) -> Vec<ContactProperty> {
+        let mut source = metadata_source(&config.db);
+        match source.get_contact_properties(config.akid) {
+            Ok(props) => props,
             Err(e) => {
-                eprintln!("Warning: Could not get contact properties: {}", e);
-                Ok(Vec::new())
+                // Database not available, return empty properties
+                if !config.quiet {
+                    eprintln!("Warning: Could not get contact properties: {}", e);
+                }
+                Vec::new()
             }
         }
     }
@@ -357,26 +1412,154 @@ impl CsvAnalyzer {
         let error_type = error.error_type();
         let internal_msg = format!("{}", error);
 
-        ErrorResponse::new(error_type, &self.config.locale, &self.charset)
+        let mut response = ErrorResponse::new(error_type, &self.config.locale, &self.charset)
             .with_internal_message(internal_msg)
             .with_location(self.current_row, self.current_col)
             .with_field(&self.current_field)
             .with_data_type(self.current_data_type)
             .with_column_count(self.current_col_count)
             .with_field_separator(self.field_delim)
-            .with_text_delimiter(self.text_sep)
+            .with_text_delimiter(self.text_sep);
+        if let Some(offset) = self.error_offset {
+            response = response.with_byte_offset(offset);
+        }
+        if matches!(
+            error_type,
+            CsvErrorType::ColumnLong | CsvErrorType::ValueLong
+        ) {
+            response = response.with_value_length(self.current_field.len());
+        }
+        if let (CsvErrorType::HeaderChar, Some(allowed)) =
+            (error_type, self.config.header_chars.as_deref())
+        {
+            if let Some(c) = disallowed_header_char(&self.current_field, allowed) {
+                response = response.with_char(c);
+            }
+        }
+
+        // Quote the offending line, current_row is its 1-based line number
+        let index = self
+            .line_numbers
+            .iter()
+            .position(|&line| line == self.current_row);
+        if let Some((index, line)) =
+            index.and_then(|i| self.source_lines.get(i).map(|line| (i, line)))
+        {
+            let first = index.saturating_sub(ERROR_CONTEXT_LINES);
+            let last = (index + ERROR_CONTEXT_LINES + 1).min(self.source_lines.len());
+            let context: Vec<&str> = self.source_lines[first..last]
+                .iter()
+                .map(String::as_str)
+                .collect();
+            response = response
+                .with_line(self.current_row, line)
+                .with_context(&context);
+        }
+
+        response
+    }
+}
+
+/// Run charset, quote, delimiter, header and column count detection on a
+/// sample with the default configuration, without a database.
+/// Fails like `analyze` on samples it cannot detect a dialect in, e.g. an
+/// empty or binary sample. Also exported as `detection::detect_all`, it lives
+/// here as it runs the analyzer's own sample preparation.
+pub fn detect_all(sample: &[u8]) -> Result<DetectionReport> {
+    CsvAnalyzer::with_properties(Config::default(), Vec::new()).detect(sample)
+}
+
+/// Line without the control characters ending it, tabs excepted as they
+/// delimit an empty last field
+fn trim_trailing_control(line: &str) -> &str {
+    line.trim_end_matches(|c: char| c.is_control() && c != '\t')
+}
+
+/// Whether a line is a comment: `prefix` after optional leading whitespace
+fn is_comment_line(line: &str, prefix: char) -> bool {
+    line.trim_start().starts_with(prefix)
+}
+
+/// Fields of a parsed CSV line
+#[derive(Default)]
+struct ParsedLine {
+    fields: Vec<String>,
+    /// Whether each field was quoted, telling an explicit `""` from a missing value
+    quoted: Vec<bool>,
+    /// Whether each field was an Excel `="..."` text value, unwrapped
+    excel_text: Vec<bool>,
+    /// Whether the line has more than `max_row_fields` fields, the extra ones
+    /// not parsed
+    too_many_fields: bool,
+}
+
+impl ParsedLine {
+    fn push(&mut self, field: &str, quoted: bool, excel_text: bool) {
+        let field = field.trim();
+        let field = if excel_text {
+            field.strip_prefix('=').unwrap_or(field)
+        } else {
+            field
+        };
+        self.fields.push(field.to_string());
+        self.quoted.push(quoted);
+        self.excel_text.push(excel_text);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::DbConfig;
+    use crate::types::constants::{ERROR_LINE_SIZE, MAX_STRING_SIZE};
+    use crate::{ColumnTypeDetector, DateOrder, DbConfig, OutputFormat};
+    use std::io::Write;
+    use std::sync::Arc;
+
+    /// Analyze CSV content written to a temporary file
+    fn analyze_content(content: &str, configure: impl FnOnce(&mut Config)) -> serde_json::Value {
+        analyze_bytes(content.as_bytes(), configure)
+    }
 
-    #[test]
-    fn test_parse_line_simple() {
-        let db_config = DbConfig::new("localhost", 5432, "test", "test", "test");
-        let config = Config::new_with_db(1, "en_US", "test.csv", db_config);
+    fn analyze_bytes(content: &[u8], configure: impl FnOnce(&mut Config)) -> serde_json::Value {
+        // No contact properties, the tests don't depend on a database
+        analyze_with_properties(content, Vec::new(), configure)
+    }
+
+    fn analyze_with_properties(
+        content: &[u8],
+        properties: Vec<ContactProperty>,
+        configure: impl FnOnce(&mut Config),
+    ) -> serde_json::Value {
+        with_analyzer(content, properties, configure, |analyzer| {
+            serde_json::from_str(&analyzer.analyze()).unwrap()
+        })
+    }
+
+    /// Run `run` on an analyzer of `content` written to a temporary file
+    fn with_analyzer<T>(
+        content: &[u8],
+        properties: Vec<ContactProperty>,
+        configure: impl FnOnce(&mut Config),
+        run: impl FnOnce(&mut CsvAnalyzer) -> T,
+    ) -> T {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+
+        let mut config = test_config(file.path().to_str().unwrap());
+        configure(&mut config);
+        run(&mut CsvAnalyzer::with_properties(config, properties))
+    }
+
+    /// Configuration of a test, its database is never connected to
+    fn test_config(filename: &str) -> Config {
+        let db_config = DbConfig::new("localhost", 5432, "test", "test", "test");
+        Config::new_with_db(1, "en_US", filename, db_config)
+    }
+
+    #[test]
+    fn test_parse_line_simple() {
+        let db_config = DbConfig::new("localhost", 5432, "test", "test", "test");
+        let config = Config::new_with_db(1, "en_US", "test.csv", db_config);
         let mut analyzer = CsvAnalyzer::new(config);
         analyzer.field_delim = ',';
         analyzer.text_sep = '"';
@@ -408,4 +1591,1803 @@ mod tests {
         let fields = analyzer.parse_line("a;b;c");
         assert_eq!(fields, vec!["a", "b", "c"]);
     }
+
+    #[test]
+    fn test_parse_line_escaped_delimiter() {
+        let db_config = DbConfig::new("localhost", 5432, "test", "test", "test");
+        let mut config = Config::new_with_db(1, "en_US", "test.csv", db_config);
+        config.escape_char = Some('\\');
+        let mut analyzer = CsvAnalyzer::new(config);
+        analyzer.field_delim = ';';
+        analyzer.text_sep = '"';
+
+        let fields = analyzer.parse_line(r"a\;b;c");
+        assert_eq!(fields, vec!["a;b", "c"]);
+    }
+
+    #[test]
+    fn test_parse_line_escape_sequences() {
+        let db_config = DbConfig::new("localhost", 5432, "test", "test", "test");
+        let mut config = Config::new_with_db(1, "en_US", "test.csv", db_config);
+        config.escape_char = Some('\\');
+        let mut analyzer = CsvAnalyzer::new(config);
+        analyzer.field_delim = ',';
+        analyzer.text_sep = '"';
+
+        assert_eq!(analyzer.parse_line(r"a\,b,c"), vec!["a,b", "c"]);
+        assert_eq!(analyzer.parse_line(r"a\\,b,c"), vec![r"a\", "b", "c"]);
+        // Escapes compose with quoting
+        assert_eq!(
+            analyzer.parse_line(r#""say \"hi\", ok",b"#),
+            vec![r#"say "hi", ok"#, "b"]
+        );
+    }
+
+    #[test]
+    fn test_parse_profile_reapplied() {
+        let filename = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/test_semicolon.csv"
+        );
+        let config = test_config(filename);
+        let mut analyzer = CsvAnalyzer::new(config.clone());
+        let detected = analyzer.analyze();
+
+        let profile = analyzer.parse_profile();
+        assert_eq!(profile.field_delimiter, ';');
+        assert!(profile.skip_header);
+        assert_eq!(profile.date_time_format.as_deref(), Some("yyyy-mm-dd"));
+
+        // Profile survives a serde round-trip
+        let json = serde_json::to_string(&profile).unwrap();
+        let profile: ParseProfile = serde_json::from_str(&json).unwrap();
+
+        let mut config = config;
+        config.parse_profile = Some(profile);
+        let mut analyzer = CsvAnalyzer::new(config);
+        assert_eq!(analyzer.analyze(), detected);
+    }
+
+    #[test]
+    fn test_percent_column_reported() {
+        let result = analyze_content(
+            "email,rate,score\njohn@example.com,12%,5\njane@example.com,3.5%,7\n",
+            |_| {},
+        );
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 2, 1]));
+        assert_eq!(
+            result["NumberFormats"],
+            serde_json::json!([null, "percent", null])
+        );
+    }
+
+    #[test]
+    fn test_currency_column_reported() {
+        let result = analyze_content(
+            "email;price\njohn@example.com;1.234,56 €\njane@example.com;€9,99\n",
+            |_| {},
+        );
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 2]));
+        assert_eq!(
+            result["NumberFormats"],
+            serde_json::json!([null, "currency"])
+        );
+        assert_eq!(result["CurrencySymbols"], serde_json::json!([null, "€"]));
+    }
+
+    #[test]
+    fn test_email_not_required() {
+        let content = "name,age\nJohn,30\nJane,25\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["Error"], CsvErrorType::EmailNotFound as u8);
+
+        let result = analyze_content(content, |config| config.require_email = false);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldNames"], serde_json::json!(["", ""]));
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1]));
+    }
+
+    #[test]
+    fn test_fullwidth_digits() {
+        let content = "email,count\njohn@example.com,１２３\njane@example.com,４５\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["DataTypes"][1], DataType::String as u8);
+
+        let result = analyze_content(content, |config| config.normalize_fullwidth = true);
+        assert_eq!(result["DataTypes"][1], DataType::Integer as u8);
+        // Values are returned as they appear in the file
+        assert_eq!(result["Data"][0][1], "１２３");
+    }
+
+    #[test]
+    fn test_utf16le_not_binary() {
+        let content = "email,name\njohn@example.com,John\njane@example.com,Jane\n";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+        assert!(is_binary_data(&bytes));
+
+        let result = analyze_bytes(&bytes, |_| {});
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["Charset"], "UTF-16LE");
+        assert_eq!(result["FieldNames"], serde_json::json!(["email", ""]));
+        assert_eq!(result["HeaderNames"][0], "email");
+    }
+
+    #[test]
+    fn test_utf16_without_bom() {
+        let content = "email,name\njohn@example.com,John\njane@example.com,Jane\n";
+        for bytes in [
+            content
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<u8>>(),
+            content.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        ] {
+            let result = analyze_bytes(&bytes, |_| {});
+            assert!(result.get("Error").is_none());
+            assert_eq!(result["HeaderNames"], serde_json::json!(["email", "name"]));
+            assert_eq!(result["Data"][1][1], "Jane");
+        }
+    }
+
+    #[test]
+    fn test_prefer_date_order() {
+        let content = "email,joined\njohn@example.com,01/02/2020\njane@example.com,03/04/2020\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["DateTimeFormat"], "dd/mm/yyyy");
+
+        let result = analyze_content(content, |config| {
+            config.prefer_date_order = Some(DateOrder::Mdy)
+        });
+        assert_eq!(result["DataTypes"][1], DataType::DateTime as u8);
+        assert_eq!(result["DateTimeFormat"], "mm/dd/yyyy");
+
+        // A day above 12 settles the order regardless of the preference
+        let result = analyze_content(
+            "email,joined\njohn@example.com,01/02/2020\njane@example.com,13/04/2020\n",
+            |config| config.prefer_date_order = Some(DateOrder::Mdy),
+        );
+        assert_eq!(result["DateTimeFormat"], "dd/mm/yyyy");
+
+        // Later rows ruling out both orders leave a String column
+        let result = analyze_content(
+            "email,joined\njohn@example.com,01/02/2020\njane@example.com,12/25/2020\n\
+             bob@example.com,25/12/2020\n",
+            |_| {},
+        );
+        assert_eq!(result["DataTypes"][1], DataType::String as u8);
+        assert!(result.get("DateTimeFormat").is_none());
+    }
+
+    #[test]
+    fn test_datetime_granularities() {
+        let result = analyze_content(
+            "email,born,last_login\n\
+             john@example.com,1990-05-01,2024-01-15 10:30\n\
+             jane@example.com,1985-11-23,2024-02-20 08:05\n",
+            |_| {},
+        );
+        assert_eq!(
+            result["Granularities"],
+            serde_json::json!([null, "date", "datetime"])
+        );
+
+        let result = analyze_content("email,name\njohn@example.com,John\n", |_| {});
+        assert!(result.get("Granularities").is_none());
+    }
+
+    #[test]
+    fn test_month_name_dates() {
+        let result = analyze_content(
+            "email,joined,renewed\n\
+             john@example.com,15 Jan 2020,\"January 15, 2021\"\n\
+             jane@example.com,3 feb 2020,\"February 3, 2021\"\n",
+            |_| {},
+        );
+        assert_eq!(result["DataTypes"][1], DataType::DateTime as u8);
+        assert_eq!(result["DataTypes"][2], DataType::DateTime as u8);
+        // The longest format of the date columns is reported
+        assert_eq!(result["DateTimeFormat"], "mmmm dd, yyyy");
+    }
+
+    #[test]
+    fn test_with_properties_skips_database() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"email,nick,score\njohn@example.com,Johnny,5\njane@example.com,Janie,7\n")
+            .unwrap();
+
+        // Unreachable database: the supplied properties can only come from the cache
+        let mut db_config = DbConfig::new("10.255.255.1", 5432, "test", "test", "test");
+        db_config.connect_timeout_secs = Some(30);
+        let config = Config::new_with_db(1, "en_US", file.path().to_str().unwrap(), db_config);
+        let properties = vec![
+            ContactProperty {
+                name: "nick".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "score".to_string(),
+                datatype: DataType::Integer,
+            },
+        ];
+
+        let start = std::time::Instant::now();
+        let mut analyzer = CsvAnalyzer::with_properties(config, properties);
+        let result: serde_json::Value = serde_json::from_str(&analyzer.analyze()).unwrap();
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "nick", "score"])
+        );
+    }
+
+    #[test]
+    fn test_match_notes() {
+        let content = b"email,age,nick\njohn@example.com,30.5,Johnny\njane@example.com,41,Janie\n";
+        // The email column keeping its name is not a mapping failure
+        let properties = vec![
+            ContactProperty {
+                name: "Email".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "Age".to_string(),
+                datatype: DataType::Integer,
+            },
+            ContactProperty {
+                name: "nick".to_string(),
+                datatype: DataType::String,
+            },
+        ];
+
+        let result = analyze_with_properties(content, properties, |_| {});
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "", "nick"])
+        );
+        assert_eq!(
+            result["MatchNotes"],
+            serde_json::json!([
+                "header 'age' matched property 'Age' but detected Float vs expected Integer"
+            ])
+        );
+
+        let result = analyze_content("email,nick\njohn@example.com,Johnny\n", |_| {});
+        assert!(result.get("MatchNotes").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_property_match() {
+        let content = b"email,First Name,Lst Name\njohn@example.com,John,Doe\n";
+        let properties = vec![
+            ContactProperty {
+                name: "FirstName".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "LastName".to_string(),
+                datatype: DataType::String,
+            },
+        ];
+
+        let result = analyze_with_properties(content, properties.clone(), |_| {});
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "FirstName", ""])
+        );
+        assert!(result.get("MatchNotes").is_none());
+
+        let result = analyze_with_properties(content, properties, |config| {
+            config.fuzzy_match_distance = Some(1)
+        });
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "FirstName", "LastName"])
+        );
+        assert_eq!(
+            result["MatchNotes"],
+            serde_json::json!(["header 'Lst Name' fuzzy-matched property 'LastName' (distance 1)"])
+        );
+    }
+
+    #[test]
+    fn test_property_mapped_once() {
+        let properties = vec![ContactProperty {
+            name: "FirstName".to_string(),
+            datatype: DataType::String,
+        }];
+        let field_names = |content: &[u8], fuzzy_match_distance| {
+            let result = analyze_with_properties(content, properties.clone(), |config| {
+                config.fuzzy_match_distance = fuzzy_match_distance
+            });
+            (result["FieldNames"].clone(), result["MatchNotes"].clone())
+        };
+
+        let (names, notes) = field_names(
+            b"email,First Name,first_name\njohn@example.com,John,Johnny\n",
+            None,
+        );
+        assert_eq!(names, serde_json::json!(["email", "FirstName", ""]));
+        assert_eq!(
+            notes,
+            serde_json::json!([
+                "header 'first_name' matched property 'FirstName' already mapped to column 2"
+            ])
+        );
+
+        // An exact match wins over an earlier normalized one
+        let (names, _) = field_names(
+            b"email,first_name,FirstName\njohn@example.com,Johnny,John\n",
+            None,
+        );
+        assert_eq!(names, serde_json::json!(["email", "", "FirstName"]));
+
+        // A normalized match wins over an earlier fuzzy one
+        let (names, notes) = field_names(
+            b"email,Frist Name,First Name\njohn@example.com,Johnny,John\n",
+            Some(2),
+        );
+        assert_eq!(names, serde_json::json!(["email", "", "FirstName"]));
+        assert_eq!(
+            notes,
+            serde_json::json!([
+                "header 'Frist Name' matched property 'FirstName' already mapped to column 3"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_preview_field_names() {
+        let properties = vec![
+            ContactProperty {
+                name: "FirstName".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "age".to_string(),
+                datatype: DataType::Integer,
+            },
+        ];
+        let analyzer = CsvAnalyzer::with_properties(test_config("test.csv"), properties);
+
+        let headers: Vec<String> = ["email", "firstname", "Age", "city", "First_Name"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        assert_eq!(
+            analyzer.preview_field_names(&headers).unwrap(),
+            vec!["", "FirstName", "age", "", ""]
+        );
+    }
+
+    #[test]
+    fn test_sqlite_metadata_source() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"email,nick,score\njohn@example.com,Johnny,5\njane@example.com,Janie,7\n")
+            .unwrap();
+
+        let db_file = tempfile::NamedTempFile::new().unwrap();
+        let conn = rusqlite::Connection::open(db_file.path()).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE contact_meta (name TEXT, datatype INTEGER, namespace INTEGER);
+             INSERT INTO contact_meta VALUES ('nick', 0, 0), ('score', 2, 0);",
+        )
+        .unwrap();
+
+        let db_config = DbConfig {
+            sqlite_path: Some(db_file.path().to_path_buf()),
+            ..DbConfig::default()
+        };
+        let config = Config::new_with_db(1, "en_US", file.path().to_str().unwrap(), db_config);
+        let mut analyzer = CsvAnalyzer::new(config);
+        let result: serde_json::Value = serde_json::from_str(&analyzer.analyze()).unwrap();
+
+        // "score" is Float in contact_meta but holds integers, so it stays unnamed
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "nick", ""])
+        );
+    }
+
+    #[test]
+    fn test_collect_warnings() {
+        let long_value = "x".repeat(MAX_STRING_SIZE + 1);
+        let content = format!(
+            "email,{}\njohn@example.com,short\njane@example.com,{}\n",
+            long_value, long_value
+        );
+
+        let result = analyze_content(&content, |_| {});
+        assert_eq!(result["Error"], CsvErrorType::ColumnLong as u8);
+
+        let result = analyze_content(&content, |config| config.collect_warnings = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(
+            result["Warnings"],
+            serde_json::json!([
+                {"Row": 1, "Col": 2, "Kind": "ColumnLong"},
+                {"Row": 3, "Col": 2, "Kind": "ValueLong"},
+            ])
+        );
+        assert_eq!(
+            result["HeaderNames"][1].as_str().unwrap().len(),
+            MAX_STRING_SIZE
+        );
+        assert_eq!(
+            result["Data"][1][1].as_str().unwrap().len(),
+            MAX_STRING_SIZE
+        );
+        assert_eq!(result["Data"][0][1], "short");
+    }
+
+    #[test]
+    fn test_long_headers_sharing_prefix() {
+        let prefix = "x".repeat(MAX_STRING_SIZE);
+        let content = format!("email,{}a,{}b\njohn@example.com,1,2\n", prefix, prefix);
+
+        let result = analyze_content(&content, |config| config.collect_warnings = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["Warnings"].as_array().unwrap().len(), 2);
+        let headers = result["HeaderNames"].as_array().unwrap();
+        assert_ne!(headers[1], headers[2]);
+        assert_eq!(headers[1], prefix.as_str());
+        let second = headers[2].as_str().unwrap();
+        assert!(second.ends_with("~2") && second.len() == MAX_STRING_SIZE);
+
+        // Every column keeps its pandas dtype
+        let result = analyze_content(&content, |config| {
+            config.collect_warnings = true;
+            config.output_format = OutputFormat::PandasDtypes;
+        });
+        assert_eq!(result.as_object().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_full_scan() {
+        // The value past the sample window turns the integer column into Float
+        let mut content = String::from("email,score\n");
+        for i in 0..10 {
+            content.push_str(&format!("user{}@example.com,{}\n", i, i));
+        }
+        content.push_str("late@example.com,2.5\n");
+
+        let result = analyze_content(&content, |config| config.scan_lines = 5);
+        assert_eq!(result["DataTypes"][1], DataType::Integer as u8);
+
+        let result = analyze_content(&content, |config| {
+            config.scan_lines = 5;
+            config.full_scan = true;
+        });
+        assert_eq!(result["DataTypes"][1], DataType::Float as u8);
+    }
+
+    #[test]
+    fn test_value_long_error_line() {
+        let long_value = "x".repeat(MAX_STRING_SIZE + 1);
+        let content = format!(
+            "email,note\njohn@example.com,short\njane@example.com,{}\nbob@example.com,fine\n",
+            long_value
+        );
+
+        let result = analyze_content(&content, |_| {});
+        assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+        assert_eq!(result["ErrorLineNumber"], 3);
+        let line = result["ErrorLine"].as_str().unwrap();
+        assert!(line.starts_with("jane@example.com,xxx"));
+        assert_eq!(line.chars().count(), ERROR_LINE_SIZE);
+        assert_eq!(result["ErrorContext"][0], "john@example.com,short");
+        assert_eq!(result["ErrorContext"][2], "bob@example.com,fine");
+    }
+
+    #[test]
+    fn test_strict_columns() {
+        let mut content = String::from("email,name\n");
+        for i in 0..9 {
+            content.push_str(&format!("user{}@example.com,User {}\n", i, i));
+        }
+        content.push_str("ragged@example.com,Ragged,extra\n");
+
+        let result = analyze_content(&content, |_| {});
+        assert!(result.get("Error").is_none());
+
+        let result = analyze_content(&content, |config| config.strict_columns = true);
+        assert_eq!(result["Error"], CsvErrorType::VariousFieldsCount as u8);
+        assert_eq!(result["ErrorRow"], 11);
+        assert!(result["ErrorMsgInternal"]
+            .as_str()
+            .unwrap()
+            .ends_with("(2 cols: 10 rows, 3 cols: 1 rows)"));
+        assert_eq!(result["ErrorLine"], "ragged@example.com,Ragged,extra");
+    }
+
+    #[test]
+    fn test_duplicate_rows() {
+        let content =
+            "email,name\njohn@example.com,John\njane@example.com,Jane\njohn@example.com,John\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("DuplicateRows").is_none());
+
+        let result = analyze_content(content, |config| config.detect_duplicate_rows = true);
+        assert_eq!(
+            result["DuplicateRows"],
+            serde_json::json!({"Count": 1, "FirstRow": 4})
+        );
+    }
+
+    #[test]
+    fn test_strict_encoding() {
+        // 0x81 0x20 is not a valid Shift_JIS sequence
+        let content = b"email,name\njohn@example.com,Jo\x81\x20\n";
+        let profile = |config: &mut Config| {
+            config.parse_profile = Some(ParseProfile {
+                charset: "shiftjis".to_string(),
+                field_delimiter: ',',
+                text_delimiter: '"',
+                skip_header: true,
+                date_time_format: None,
+                skip_lines: 0,
+            })
+        };
+
+        let result = analyze_bytes(content, profile);
+        assert!(result.get("Error").is_none());
+
+        let result = analyze_bytes(content, |config| {
+            profile(config);
+            config.strict_encoding = true;
+        });
+        assert_eq!(result["Error"], CsvErrorType::Encoding as u8);
+    }
+
+    #[test]
+    fn test_max_decode_error_ratio() {
+        let profile = |config: &mut Config| {
+            config.parse_profile = Some(ParseProfile {
+                charset: "shiftjis".to_string(),
+                field_delimiter: ',',
+                text_delimiter: '"',
+                skip_header: true,
+                date_time_format: None,
+                skip_lines: 0,
+            });
+            config.max_decode_error_ratio = Some(0.01);
+        };
+
+        // A single bad byte in a large sample is tolerated
+        let mut content = b"email,name\n".to_vec();
+        for i in 0..100 {
+            content.extend_from_slice(format!("user{}@example.com,User\n", i).as_bytes());
+        }
+        content.extend_from_slice(b"jo@example.com,Jo\x81\x20\n");
+        let result = analyze_bytes(&content, profile);
+        assert!(result.get("Error").is_none());
+
+        // 0x81 0x20 is not a valid Shift_JIS sequence
+        let content = b"email,name\njohn@example.com,\x81\x20\x81\x20\x81\x20\n";
+        let result = analyze_bytes(content, profile);
+        assert_eq!(result["Error"], CsvErrorType::Encoding as u8);
+    }
+
+    #[test]
+    fn test_email_property_precedence() {
+        let content = b"work_email,name\njohn@corp.com,John\njane@corp.com,Jane\n";
+        let properties = vec![ContactProperty {
+            name: "work_email".to_string(),
+            datatype: DataType::String,
+        }];
+
+        let analyze = |precedence: bool| {
+            analyze_with_properties(content, properties.clone(), |config| {
+                config.email_property_precedence = precedence
+            })
+        };
+
+        assert_eq!(
+            analyze(false)["FieldNames"],
+            serde_json::json!(["email", ""])
+        );
+        assert_eq!(
+            analyze(true)["FieldNames"],
+            serde_json::json!(["work_email", ""])
+        );
+    }
+
+    #[test]
+    fn test_force_charset() {
+        // cp1251 "Рё" (D0 B8) is also valid UTF-8 for "и"
+        let content = b"email,name\njohn@example.com,\xD0\xB8\n";
+
+        let result = analyze_bytes(content, |_| {});
+        assert_eq!(result["Charset"], "utf8");
+        assert_eq!(result["Data"][0][1], "и");
+
+        let result = analyze_bytes(content, |config| {
+            config.force_charset = Some("cp1251".to_string())
+        });
+        assert_eq!(result["Charset"], "cp1251");
+        assert_eq!(result["Data"][0][1], "Рё");
+    }
+
+    #[test]
+    fn test_force_header() {
+        // The email-looking token in the first row would make it data
+        let content = "email,reply_to@example.com\njohn@example.com,John\njane@example.com,Jane\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["SkipHeader"], false);
+
+        let result = analyze_content(content, |config| config.force_header = Some(true));
+        assert_eq!(result["SkipHeader"], true);
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "reply_to@example.com"])
+        );
+        assert_eq!(result["Data"][0][0], "john@example.com");
+    }
+
+    #[test]
+    fn test_provided_headers() {
+        let content = b"john@example.com,J. Smith,30\njane@example.com,A. Doe,25\n";
+        let properties = vec![ContactProperty {
+            name: "full_name".to_string(),
+            datatype: DataType::String,
+        }];
+        let analyze = |headers: &[&str]| {
+            analyze_with_properties(content, properties.clone(), |config| {
+                config.provided_headers = Some(headers.iter().map(|h| h.to_string()).collect())
+            })
+        };
+
+        let result = analyze(&["email", "full_name", "age"]);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["SkipHeader"], false);
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "full_name", "age"])
+        );
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "full_name", ""])
+        );
+        assert_eq!(result["Data"][0][0], "john@example.com");
+
+        let result = analyze(&["email", "full_name"]);
+        assert_eq!(
+            result["ErrorMsgInternal"],
+            "Configuration error: 2 headers provided for 3 columns"
+        );
+
+        // A detected header row wins
+        let result = analyze_content("email,name\njohn@example.com,John\n", |config| {
+            config.provided_headers = Some(vec!["a".to_string(), "b".to_string()])
+        });
+        assert_eq!(result["HeaderNames"], serde_json::json!(["email", "name"]));
+    }
+
+    #[test]
+    fn test_detect_url_columns() {
+        let content = "email,website,bio\n\
+                       john@example.com,https://example.com,Likes https://example.org\n\
+                       jane@example.com,,Writes about example.com\n\
+                       bob@example.com,http://blog.example.net/bob,Plain text\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("UrlColumns").is_none());
+
+        let result = analyze_content(content, |config| config.detect_url_columns = true);
+        assert_eq!(result["UrlColumns"], serde_json::json!([1]));
+
+        let result = analyze_content(
+            "email,bio\njohn@example.com,Plain text\njane@example.com,More text\n",
+            |config| config.detect_url_columns = true,
+        );
+        assert!(result.get("UrlColumns").is_none());
+    }
+
+    #[derive(Debug)]
+    struct CountryCodeDetector;
+
+    impl ColumnTypeDetector for CountryCodeDetector {
+        fn detect(&self, values: &[&str]) -> Option<String> {
+            values
+                .iter()
+                .filter(|v| !v.is_empty())
+                .all(|v| ["DE", "FR", "GB", "US"].contains(v))
+                .then(|| "country_code".to_string())
+        }
+    }
+
+    #[test]
+    fn test_type_detectors() {
+        let content = "email,country,age
+                       john@example.com,US,30
+                       jane@example.com,,25
+                       bob@example.com,FR,41
+";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("SemanticTypes").is_none());
+
+        let result = analyze_content(content, |config| {
+            config.type_detectors = vec![Arc::new(CountryCodeDetector)]
+        });
+        assert_eq!(
+            result["SemanticTypes"],
+            serde_json::json!([null, "country_code", null])
+        );
+        // Not a contact property, the column stays unmapped
+        assert_eq!(result["FieldNames"], serde_json::json!(["email", "", ""]));
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 0, 1]));
+    }
+
+    #[test]
+    fn test_analyze_rows() {
+        let to_rows = |rows: &[&[&str]]| -> Vec<Vec<String>> {
+            rows.iter()
+                .map(|row| row.iter().map(|v| v.to_string()).collect())
+                .collect()
+        };
+        let rows = to_rows(&[
+            &["john@example.com", "Smith, John", "30", "1.5", "true"],
+            &["jane@example.com", "Doe, Jane", "25", "2.25", "false"],
+        ]);
+        let analyze = |headers: Option<Vec<String>>, rows: Vec<Vec<String>>| {
+            let mut analyzer = CsvAnalyzer::with_properties(test_config("unused.csv"), Vec::new());
+            let result: serde_json::Value =
+                serde_json::from_str(&analyzer.analyze_rows(headers, rows)).unwrap();
+            result
+        };
+
+        let headers = ["email", "name", "age", "score", "active"];
+        let result = analyze(Some(headers.map(String::from).to_vec()), rows.clone());
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["SkipHeader"], true);
+        assert_eq!(result["HeaderNames"], serde_json::json!(headers));
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 0, 1, 2, 3]));
+        assert_eq!(result["FieldNames"][0], "email");
+        assert_eq!(result["Data"][0][1], "Smith, John");
+
+        // Without headers, the first row is checked for one
+        let result = analyze(None, rows);
+        assert_eq!(result["SkipHeader"], false);
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 0, 1, 2, 3]));
+        assert_eq!(result["Data"].as_array().unwrap().len(), 2);
+
+        let result = analyze(None, Vec::new());
+        assert_eq!(result["Error"], CsvErrorType::Sample as u8);
+    }
+
+    #[test]
+    fn test_header_only() {
+        let properties = vec![ContactProperty {
+            name: "age".to_string(),
+            datatype: DataType::Integer,
+        }];
+        let result = analyze_with_properties(b"email,name,age\n", properties, |_| {});
+
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["SkipHeader"], true);
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "name", "age"])
+        );
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "", "age"])
+        );
+        // Columns without values take the type of their property
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 0, 1]));
+        assert!(result.get("Data").is_none());
+    }
+
+    #[test]
+    fn test_header_chars() {
+        let content = "email,first_name,order #\njohn@example.com,John,12\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("Error").is_none());
+
+        let result = analyze_content(content, |config| {
+            config.header_chars = Some("_ ".to_string())
+        });
+        assert_eq!(result["Error"], CsvErrorType::HeaderChar as u8);
+        assert_eq!(result["ErrorRow"], 1);
+        assert_eq!(result["ErrorColumn"], 3);
+        assert_eq!(result["ErrorField"], "order #");
+        assert_eq!(result["ErrorChar"], "#");
+
+        let result = analyze_content(content, |config| {
+            config.header_chars = Some("_ #".to_string())
+        });
+        assert!(result.get("Error").is_none());
+
+        // A header too long, but only truncated with warnings, is checked too
+        let content = format!(
+            "email,{}#\njohn@example.com,12\n",
+            "x".repeat(MAX_STRING_SIZE)
+        );
+        let result = analyze_content(&content, |config| {
+            config.header_chars = Some("_ ".to_string());
+            config.collect_warnings = true;
+        });
+        assert_eq!(result["Error"], CsvErrorType::HeaderChar as u8);
+        assert_eq!(result["ErrorColumn"], 2);
+        assert_eq!(result["ErrorChar"], "#");
+    }
+
+    #[test]
+    fn test_type_names_as_strings() {
+        let content = "email,age,active\njohn@example.com,30,true\njane@example.com,25,false\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1, 3]));
+
+        let result = analyze_content(content, |config| config.type_names_as_strings = true);
+        assert_eq!(
+            result["DataTypes"],
+            serde_json::json!(["String", "Integer", "Boolean"])
+        );
+        assert_eq!(result["Data"][0][1], "30");
+    }
+
+    #[test]
+    fn test_mixed_line_endings() {
+        let content =
+            "email,name\r\njohn@example.com,John\r\njane@example.com,Jane\nbob@example.com,Bob\r\n";
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("Error").is_none());
+        assert_eq!(
+            result["Warnings"],
+            serde_json::json!([{"Row": 3, "Col": 0, "Kind": "MixedLineEndings"}])
+        );
+        assert_eq!(
+            result["Data"][1],
+            serde_json::json!(["jane@example.com", "Jane"])
+        );
+
+        let result = analyze_content(&content.replace("\r\n", "\n"), |_| {});
+        assert!(result.get("Warnings").is_none());
+    }
+
+    #[test]
+    fn test_infer_types_disabled() {
+        let content = "email,age,price,paid,joined,share\n\
+                       john@example.com,30,$1.50,true,2024-01-31,12%\n\
+                       jane@example.com,25,$2.25,false,2024-02-29,7%\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1, 2, 3, 4, 2]));
+
+        let result = analyze_content(content, |config| config.infer_types = false);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 0, 0, 0, 0, 0]));
+        assert_eq!(result["FieldNames"][0], "email");
+        assert!(result.get("DateTimeFormat").is_none());
+        assert!(result.get("NumberFormats").is_none());
+        assert!(result.get("CurrencySymbols").is_none());
+    }
+
+    #[test]
+    fn test_strip_nul_bytes() {
+        let content = b"email,name\njohn@example.com,Jo\0hn\njane@example.com,Jane\n";
+
+        let result = analyze_bytes(content, |_| {});
+        assert_eq!(result["Data"][0][1], "Jo\u{0}hn");
+
+        let result = analyze_bytes(content, |config| config.strip_nul_bytes = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["Data"][0][1], "John");
+        assert_eq!(
+            result["Warnings"],
+            serde_json::json!([{"Row": 2, "Col": 0, "Kind": "NulBytes"}])
+        );
+    }
+
+    #[test]
+    fn test_timings() {
+        let content = b"email,name,age\njohn@example.com,John,30\njane@example.com,Jane,25\n";
+        let run = |analyzer: &mut CsvAnalyzer| {
+            analyzer.analyze();
+            analyzer.timings().cloned()
+        };
+        assert!(with_analyzer(content, Vec::new(), |_| {}, run).is_none());
+
+        let timings = with_analyzer(
+            content,
+            Vec::new(),
+            |config| config.profile_timings = true,
+            run,
+        )
+        .unwrap();
+        assert!(timings.sample_read.is_some());
+        assert!(timings.charset.is_some());
+        assert!(timings.quote.is_some());
+        assert!(timings.delimiter.is_some());
+        assert!(timings.header.is_some());
+        assert!(timings.column_validation.is_some());
+        assert!(timings.type_detection.is_some());
+        assert!(timings.db.is_some());
+
+        let json = serde_json::to_value(&timings).unwrap();
+        assert_eq!(json.as_object().unwrap().len(), 8);
+        assert!(json["SampleRead"].is_f64());
+
+        let timings = StageTimings {
+            db: Some(std::time::Duration::from_micros(1500)),
+            ..StageTimings::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&timings).unwrap(),
+            serde_json::json!({"Db": 1.5})
+        );
+    }
+
+    #[test]
+    fn test_error_byte_offset() {
+        let result = analyze_bytes(b"ab\x00\x01\x02\x03\x04\n", |_| {});
+        assert_eq!(result["Error"], CsvErrorType::Binary as u8);
+        assert_eq!(result["ErrorByteOffset"], 2);
+
+        // A blob after a clean header line
+        let mut content = b"email,name\n".to_vec();
+        content.extend((0..32u8).cycle().take(100));
+        let result = analyze_bytes(&content, |_| {});
+        assert_eq!(result["Error"], CsvErrorType::Binary as u8);
+        assert_eq!(result["ErrorByteOffset"], 11);
+
+        // No complete line in the sample
+        let result = analyze_bytes(b"email,name", |_| {});
+        assert_eq!(result["Error"], CsvErrorType::Sample as u8);
+        assert_eq!(result["ErrorByteOffset"], 10);
+
+        let result = analyze_content("email,name\njohn@example.com,John\n", |_| {});
+        assert!(result.get("ErrorByteOffset").is_none());
+    }
+
+    #[test]
+    fn test_error_value_length() {
+        let content = format!(
+            "email,name\njohn@example.com,{}\n",
+            "x".repeat(MAX_STRING_SIZE + 200)
+        );
+        let result = analyze_content(&content, |_| {});
+        assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+        assert_eq!(result["ErrorValueLength"], MAX_STRING_SIZE + 200);
+        assert!(result.get("ErrorByteOffset").is_none());
+    }
+
+    #[test]
+    fn test_units_row() {
+        let content =
+            "email,mass,length\n,kg,m\njohn@example.com,1.5,20\njane@example.com,2.25,31\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("UnitsRow").is_none());
+        assert_eq!(result["DataTypes"][1], DataType::String as u8);
+
+        let result = analyze_content(content, |config| config.detect_units_row = true);
+        assert_eq!(result["UnitsRow"], serde_json::json!(["", "kg", "m"]));
+        assert_eq!(result["DataTypes"][1], DataType::Float as u8);
+        assert_eq!(result["DataTypes"][2], DataType::Integer as u8);
+        assert_eq!(result["Data"][0][0], "john@example.com");
+    }
+
+    #[test]
+    fn test_trailing_delimiter_off_by_one() {
+        let result = analyze_content(
+            "email,name,age\n\
+             john@example.com,John,30,\n\
+             jane@example.com,Jane,25,\n\
+             bob@example.com,Bob,40,\n",
+            |_| {},
+        );
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["SkipHeader"], true);
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "name", "age"])
+        );
+        assert_eq!(result["DataTypes"][2], DataType::Integer as u8);
+        assert_eq!(
+            result["Data"][0],
+            serde_json::json!(["john@example.com", "John", "30"])
+        );
+
+        let result = analyze_content(
+            "email,name,age,\njohn@example.com,John,30\njane@example.com,Jane,25\n",
+            |_| {},
+        );
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "name", "age"])
+        );
+        assert_eq!(result["Data"][1][2], "25");
+    }
+
+    #[test]
+    fn test_max_row_fields() {
+        let mut content = String::from("email,name\n");
+        for i in 0..20 {
+            content.push_str(&format!("user{}@example.com,User {}\n", i, i));
+        }
+        // One malformed row among otherwise consistent ones
+        content.push_str(&format!("x{}\n", ",".repeat(100_000)));
+
+        // Large enough a sample for the whole row
+        let result = analyze_content(&content, |config| config.max_bytes = 200_000);
+        assert_eq!(result["Error"], CsvErrorType::TooMuchColumns as u8);
+        assert_eq!(result["ErrorRow"], 22);
+
+        let result = analyze_content(&content, |config| {
+            config.max_bytes = 200_000;
+            config.max_row_fields = 200_000;
+        });
+        assert!(result.get("Error").is_none());
+
+        // Rows past the sample are guarded by the full scan
+        let result = analyze_content(&content, |config| {
+            config.scan_lines = 10;
+            config.full_scan = true;
+        });
+        assert_eq!(result["Error"], CsvErrorType::TooMuchColumns as u8);
+    }
+
+    #[test]
+    fn test_sample_bytes() {
+        // Rows of about 30KB, 150 columns of 200 characters
+        let mut content = String::from("email");
+        for i in 1..150 {
+            content.push_str(&format!(",col{}", i));
+        }
+        content.push('\n');
+        for i in 0..4 {
+            content.push_str(&format!("user{}@example.com", i));
+            for _ in 1..150 {
+                content.push(',');
+                content.push_str(&"x".repeat(200));
+            }
+            content.push('\n');
+        }
+
+        // The default sample cuts the second row, which is left out
+        let result = analyze_content(&content, |_| {});
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["Data"].as_array().unwrap().len(), 1);
+
+        let result = analyze_content(&content, |config| config.max_bytes = 200_000);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["DataTypes"].as_array().unwrap().len(), 150);
+        assert_eq!(result["Data"].as_array().unwrap().len(), 4);
+
+        // The line cap still ends the sample first
+        let result = analyze_content(&content, |config| {
+            config.max_bytes = 200_000;
+            config.scan_lines = 2;
+        });
+        assert!(result.get("Error").is_none());
+
+        let result = analyze_content(&content, |config| config.max_bytes = 0);
+        assert_eq!(
+            result["ErrorMsgInternal"],
+            "Configuration error: Sample size must be positive"
+        );
+    }
+
+    #[test]
+    fn test_sample_cut_mid_record() {
+        let content = "email,name,age\n\
+                       john@example.com,John,30\n\
+                       jane@example.com,Jane,25\n\
+                       bob@example.com,Bob,40";
+
+        // The limit lands inside Jane's row
+        let cap = content.find("Jane").unwrap();
+        let result = analyze_content(content, |config| config.max_bytes = cap);
+        assert!(result.get("Error").is_none());
+        assert_eq!(
+            result["Data"],
+            serde_json::json!([["john@example.com", "John", "30"]])
+        );
+
+        // A last record without newline ending right at the limit is complete
+        let result = analyze_content(content, |config| config.max_bytes = content.len());
+        assert_eq!(result["Data"].as_array().unwrap().len(), 3);
+        assert_eq!(result["Data"][2][2], "40");
+    }
+
+    #[test]
+    fn test_sample_cut_utf16() {
+        let mut content = String::from("email,age\r\n");
+        for i in 0..3000 {
+            content.push_str(&format!("user{}@example.com,{}\r\n", i, 20 + i % 50));
+        }
+        assert!(content.len() * 2 > MAX_BYTES);
+        let mut data = vec![0xFF, 0xFE];
+        data.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+
+        let result = analyze_bytes(&data, |_| {});
+        assert_eq!(result["Charset"], "UTF-16LE");
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1]));
+
+        // Ending right after a line feed byte of the line limit
+        let result = analyze_bytes(&data, |config| {
+            config.scan_lines = 20;
+            config.strict_encoding = true;
+        });
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1]));
+
+        let result = analyze_bytes(&data, |config| config.strict_encoding = true);
+        assert!(result.get("Error").is_none());
+    }
+
+    #[test]
+    fn test_last_row_error_trailing_newline() {
+        let long = "x".repeat(MAX_STRING_SIZE + 1);
+        let content = format!(
+            "email,name,city\n\
+             john@example.com,John,Paris\n\
+             jane@example.com,Jane,Rome\n\
+             bob@example.com,{},Oslo",
+            long
+        );
+        for ending in ["", "\n", "\r\n"] {
+            let content = format!("{}{}", content, ending);
+            let result = analyze_content(&content, |_| {});
+            assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+            assert_eq!(result["ErrorRow"], 4, "ending {:?}", ending);
+
+            // Beyond the sample, found by the full scan
+            let content = content.replace(&long, "Bob,Oslo,extra");
+            let result = analyze_content(&content, |config| {
+                config.scan_lines = 2;
+                config.full_scan = true;
+                config.max_row_fields = 4;
+            });
+            assert_eq!(result["Error"], CsvErrorType::TooMuchColumns as u8);
+            assert_eq!(result["ErrorRow"], 4, "ending {:?}", ending);
+        }
+    }
+
+    #[test]
+    fn test_custom_limits() {
+        let content = format!(
+            "email,a,b,c\njohn@example.com,1,2,{}\n",
+            "x".repeat(MAX_STRING_SIZE + 200)
+        );
+
+        let result = analyze_content(&content, |config| config.max_string_size = 4000);
+        assert!(result.get("Error").is_none());
+
+        let result = analyze_content(&content, |config| {
+            config.max_string_size = 4000;
+            config.max_columns = 3;
+        });
+        assert_eq!(result["Error"], CsvErrorType::TooMuchColumns as u8);
+
+        let result = analyze_content(
+            "email,name\njohn@example.com,Johnny Appleseed Junior\n",
+            |config| config.max_string_size = 20,
+        );
+        assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+        assert_eq!(result["ErrorValueLength"], 23);
+    }
+
+    #[test]
+    fn test_emit_csv_round_trip() {
+        let emit_csv = |content: &str| -> String {
+            let mut config = test_config("");
+            config.emit = Emit::Csv;
+            CsvAnalyzer::with_properties(config, Vec::new()).analyze_bytes(content.as_bytes())
+        };
+        let content = "email;name;city\njohn@example.com;\"Smith, John\";Paris\n\"jane@example.com\" ; Jane ;\"Saint-Denis; Réunion\"\n";
+
+        let csv = emit_csv(content);
+        assert_eq!(
+            csv,
+            "email;name;city\r\njohn@example.com;Smith, John;Paris\r\njane@example.com;Jane;\"Saint-Denis; Réunion\"\r\n"
+        );
+
+        // Canonical output parses back to the same rows
+        assert_eq!(emit_csv(&csv), csv);
+    }
+
+    #[test]
+    fn test_single_column_file() {
+        let result = analyze_content("email\njohn@example.com\njane@example.com\n", |_| {});
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldSeparator"], "");
+        assert_eq!(result["SingleColumn"], true);
+
+        let result = analyze_content("email,name\njohn@example.com,John\n", |_| {});
+        assert!(result.get("SingleColumn").is_none());
+    }
+
+    #[test]
+    fn test_invalid_akid() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"email\njohn@example.com\n").unwrap();
+
+        for akid in [0, -5] {
+            // The database settings point nowhere, no connection is attempted
+            let db_config = DbConfig::new("invalid.localdomain", 1, "test", "test", "test");
+            let config =
+                Config::new_with_db(akid, "en_US", file.path().to_str().unwrap(), db_config);
+            let result: serde_json::Value =
+                serde_json::from_str(&CsvAnalyzer::new(config).analyze()).unwrap();
+
+            assert_eq!(result["Error"], CsvErrorType::Process as u8);
+            assert!(result["ErrorMsgInternal"]
+                .as_str()
+                .unwrap()
+                .contains("Invalid account id"));
+        }
+    }
+
+    #[test]
+    fn test_detect_charset_only() {
+        let bom = b"\xEF\xBB\xBFemail,name\njohn@example.com,Jos\xC3\xA9\n";
+        assert_eq!(CsvAnalyzer::detect_charset_only(bom), "UTF-8BOM");
+
+        let ascii = "email,name\njohn@example.com,John\n".repeat(500);
+        assert_eq!(CsvAnalyzer::detect_charset_only(ascii.as_bytes()), "ansi");
+
+        let utf8 = "email,name\njohn@example.com,José\n";
+        assert_eq!(CsvAnalyzer::detect_charset_only(utf8.as_bytes()), "utf8");
+    }
+
+    #[test]
+    fn test_trailing_blank_lines() {
+        let content =
+            "email,name,age\njohn@example.com,John,30\njane@example.com,Jane,25\n \n\n\t\n";
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldNames"].as_array().unwrap().len(), 3);
+        assert_eq!(result["Data"].as_array().unwrap().len(), 2);
+
+        let result = analyze_content(content, |config| config.full_scan = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["DataTypes"][2], DataType::Integer as u8);
+    }
+
+    #[test]
+    fn test_expected_columns() {
+        let content = "email,name,age\n\
+                       john@example.com,John\n\
+                       jane@example.com,Jane,25,extra,more\n\
+                       jim@example.com\n\
+                       joe@example.com,Joe,40\n";
+
+        // Too ragged for the detected column count
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["Error"], CsvErrorType::VariousFieldsCount as u8);
+        assert_eq!(
+            result["ErrorMsgInternal"],
+            "CSV error: Can not determine the number of columns. Too much variation \
+             in column count (1 cols: 1 rows, 2 cols: 1 rows, 3 cols: 2 rows, 5 cols: 1 rows)"
+        );
+
+        let result = analyze_content(content, |config| config.expected_columns = Some(3));
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldNames"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            result["Data"],
+            serde_json::json!([
+                ["john@example.com", "John", ""],
+                ["jane@example.com", "Jane", "25"],
+                ["jim@example.com", "", ""],
+                ["joe@example.com", "Joe", "40"]
+            ])
+        );
+        assert_eq!(
+            result["Warnings"],
+            serde_json::json!([{"Row": 3, "Col": 4, "Kind": "ExtraColumns"}])
+        );
+    }
+
+    #[test]
+    fn test_trim_trailing_empty_column() {
+        let content = "email,name,age,\njohn@example.com,John,30,\njane@example.com,Jane,25,\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["HeaderNames"].as_array().unwrap().len(), 4);
+
+        let result = analyze_content(content, |config| config.trim_trailing_empty_column = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "name", "age"])
+        );
+        assert_eq!(result["DataTypes"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            result["Data"][0],
+            serde_json::json!(["john@example.com", "John", "30"])
+        );
+    }
+
+    #[test]
+    fn test_skip_lines() {
+        let content = "Contacts export\nGenerated on 2024-03-01\n\n\
+                       email;name;age\n\
+                       john@example.com;John;30\n\
+                       jane@example.com;Jane;25\n";
+
+        let result = analyze_content(content, |config| config.skip_lines = 3);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldSeparator"], "3B");
+        assert_eq!(result["SkipHeader"], true);
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "name", "age"])
+        );
+        assert_eq!(result["Data"].as_array().unwrap().len(), 2);
+
+        // Error rows count the skipped lines
+        let content = content.replace("Jane", &"x".repeat(MAX_STRING_SIZE + 1));
+        let result = analyze_content(&content, |config| config.skip_lines = 3);
+        assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+        assert_eq!(result["ErrorRow"], 6);
+        assert!(result["ErrorLine"]
+            .as_str()
+            .unwrap()
+            .starts_with("jane@example.com;xxx"));
+    }
+
+    #[test]
+    fn test_sep_directive() {
+        // The sep= line is skipped rather than taken as the header
+        let content = "sep=;\n\
+                       email;name\n\
+                       john@example.com;Doe, John\n\
+                       jane@example.com;Doe, Jane\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldSeparator"], "3B");
+        assert_eq!(result["HeaderNames"], serde_json::json!(["email", "name"]));
+        assert_eq!(result["Data"][0][1], "Doe, John");
+    }
+
+    #[test]
+    fn test_unwrap_excel_text() {
+        let content = "email,code\njohn@example.com,=\"001\"\njane@example.com,=\"002\"\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["Data"][0][1], "=001");
+
+        let result = analyze_content(content, |config| config.unwrap_excel_text = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["Data"][0][1], "001");
+        assert_eq!(result["Data"][1][1], "002");
+        assert_eq!(result["DataTypes"][1], DataType::String as u8);
+    }
+
+    #[test]
+    fn test_comment_lines() {
+        let long_value = "x".repeat(MAX_STRING_SIZE + 1);
+        let content = format!(
+            "# Contacts export\n\
+             email;name;age\n\
+             # checked 2024-03-01, see notes\n\
+             john@example.com;John;30\n\
+             \t# a, b, c\n\
+             jane@example.com;Jane;25\n\
+             # end\n\
+             jim@example.com;{};40\n",
+            long_value
+        );
+
+        let result = analyze_content(&content, |config| config.comment_prefix = Some('#'));
+        assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+        // Line numbers of the file, comments included
+        assert_eq!(result["ErrorRow"], 8);
+        assert_eq!(result["ErrorContext"][0], "jane@example.com;Jane;25");
+
+        let content = content.replace(&long_value, "Jim");
+        let result = analyze_content(&content, |config| config.comment_prefix = Some('#'));
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["FieldSeparator"], "3B");
+        assert_eq!(
+            result["HeaderNames"],
+            serde_json::json!(["email", "name", "age"])
+        );
+        assert_eq!(result["Data"].as_array().unwrap().len(), 3);
+        assert_eq!(result["DataTypes"][2], DataType::Integer as u8);
+
+        let result = analyze_content(&content, |config| {
+            config.comment_prefix = Some('#');
+            config.full_scan = true;
+        });
+        assert_eq!(result["DataTypes"][2], DataType::Integer as u8);
+    }
+
+    #[test]
+    fn test_charset_label() {
+        let content = b"email,name\njohn@example.com,Ren\xE9\n";
+        let result = analyze_bytes(content, |config| {
+            config.force_charset = Some("cp1252".to_string());
+        });
+        assert!(result.get("CharsetLabel").is_none());
+
+        let result = analyze_bytes(content, |config| {
+            config.force_charset = Some("cp1252".to_string());
+            config.report_charset_label = true;
+        });
+        assert_eq!(result["Charset"], "cp1252");
+        assert_eq!(result["CharsetLabel"], "Windows-1252");
+    }
+
+    #[test]
+    fn test_strip_trailing_control() {
+        let content =
+            "email,name,age\x1A\njohn@example.com,John,30\x1A\njane@example.com,Jane,25\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["Data"][0][2], "30\u{1A}");
+
+        let result = analyze_content(content, |config| config.strip_trailing_control = true);
+        assert!(result.get("Error").is_none());
+        assert_eq!(result["HeaderNames"][2], "age");
+        assert_eq!(result["Data"][0][2], "30");
+        assert_eq!(result["DataTypes"][2], DataType::Integer as u8);
+        assert_eq!(
+            result["Warnings"],
+            serde_json::json!([
+                {"Row": 1, "Col": 0, "Kind": "ControlChars"},
+                {"Row": 2, "Col": 0, "Kind": "ControlChars"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_expected_types() {
+        let content = "email,age,score\njohn@example.com,thirty,1\njane@example.com,25,2\n";
+
+        let result = analyze_content(content, |config| {
+            config.expected_types = vec![(3, DataType::Integer)]
+        });
+        assert!(result.get("Error").is_none());
+
+        let result = analyze_content(content, |config| {
+            config.expected_types = vec![(2, DataType::Integer)]
+        });
+        assert_eq!(result["Error"], CsvErrorType::TypeMismatch as u8);
+        assert_eq!(result["ErrorColumn"], 2);
+        assert_eq!(result["ErrorField"], "age");
+        assert_eq!(result["ErrorDataType"], DataType::String as u8);
+
+        let result = analyze_content(content, |config| {
+            config.expected_types = vec![(2, DataType::Integer)];
+            config.collect_warnings = true;
+        });
+        assert_eq!(
+            result["Warnings"],
+            serde_json::json!([{"Row": 0, "Col": 2, "Kind": "TypeMismatch"}])
+        );
+
+        let result = analyze_content(content, |config| {
+            config.expected_types = vec![(4, DataType::Integer)]
+        });
+        assert_eq!(result["Error"], CsvErrorType::Process as u8);
+    }
+
+    #[test]
+    fn test_null_counts() {
+        let content = "email,name,city,zip\njohn@example.com,,Paris,75001\njane@example.com,  ,,75002\nbob@example.com,Bob,Rome,\n";
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("NullCounts").is_none());
+
+        let result = analyze_content(content, |config| config.report_null_counts = true);
+        assert_eq!(result["NullCounts"], serde_json::json!([0, 2, 1, 1]));
+    }
+
+    #[test]
+    fn test_column_stats() {
+        let content = "email,age\njohn@example.com,30\njane@example.com,25\nbob@example.com,30\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("ColumnStats").is_none());
+
+        let result = analyze_content(content, |config| config.compute_stats = true);
+        assert_eq!(
+            result["ColumnStats"],
+            serde_json::json!([
+                {"Distinct": 3},
+                {"Min": 25.0, "Max": 30.0, "Distinct": 2},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_estimated_rows() {
+        let mut content = String::from("email,name,score\n");
+        for i in 0..20_000 {
+            content.push_str(&format!(
+                "user{:05}@example.com,{},{}\n",
+                i,
+                "n".repeat(i % 13),
+                i * 7 % 1000
+            ));
+        }
+
+        let result = analyze_content(&content, |_| {});
+        assert!(result.get("EstimatedRows").is_none());
+
+        let result = analyze_content(&content, |config| config.estimate_rows = true);
+        let estimate = result["EstimatedRows"].as_u64().unwrap();
+        assert!(
+            (19_000..=21_000).contains(&estimate),
+            "estimate {} too far from 20000",
+            estimate
+        );
+
+        // Sample holding the whole file: exact count
+        let result = analyze_content("email\na@example.com\nb@example.com\n", |config| {
+            config.estimate_rows = true
+        });
+        assert_eq!(result["EstimatedRows"], 2);
+    }
+
+    #[test]
+    fn test_id_column() {
+        let content = "id,email,name\n1,john@example.com,John\n2,jane@example.com,Jane\n3,bob@example.com,John\n";
+
+        let result = analyze_content(content, |_| {});
+        assert!(result.get("IdColumn").is_none());
+
+        let result = analyze_content(content, |config| config.detect_id_column = true);
+        assert_eq!(result["IdColumn"], 0);
+        assert_eq!(
+            result["IdSequence"],
+            serde_json::json!({"Start": 1, "Gaps": false})
+        );
+
+        let content = "email,id\njohn@example.com,0\njane@example.com,2\nbob@example.com,3\n";
+        let result = analyze_content(content, |config| config.detect_id_column = true);
+        assert_eq!(result["IdColumn"], 1);
+        assert_eq!(
+            result["IdSequence"],
+            serde_json::json!({"Start": 0, "Gaps": true})
+        );
+    }
+
+    #[test]
+    fn test_null_for_missing() {
+        let content = "email,name,city\njohn@example.com,,Paris\njane@example.com,\"\",Rome\n";
+
+        let result = analyze_content(content, |_| {});
+        assert_eq!(result["Data"][0][1], "");
+        assert_eq!(result["Data"][1][1], "");
+
+        let result = analyze_content(content, |config| config.null_for_missing = true);
+        assert_eq!(result["Data"][0][1], serde_json::Value::Null);
+        assert_eq!(result["Data"][1][1], "");
+        assert_eq!(result["Data"][0][2], "Paris");
+
+        let result = analyze_content(content, |config| {
+            config.null_for_missing = true;
+            config.output_format = OutputFormat::JsonObjects;
+        });
+        assert_eq!(result["Data"][0]["name"], serde_json::Value::Null);
+        assert_eq!(result["Data"][1]["name"], "");
+    }
+
+    #[test]
+    fn test_unseparated_international_phone_column() {
+        let result = analyze_content(
+            "email,tel\njohn@example.com,+4930123456\njane@example.com,+4989765432\n",
+            |_| {},
+        );
+        assert_eq!(result["DataTypes"][1], DataType::String as u8);
+        assert_eq!(result["FieldNames"][1], "phone");
+        assert_eq!(result["Data"][0][1], "+4930123456");
+    }
+
+    #[test]
+    fn test_phone_column() {
+        let content = "email,tel,mobile,name\njohn@example.com,+1 555-123-4567,06 12 34 56 78,John\njane@example.com,0044 20 7946 0000,06 98 76 54 32,Jane\n";
+        let result = analyze_content(content, |_| {});
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "phone", "", ""])
+        );
+
+        // A matching contact property keeps its name
+        let properties = vec![ContactProperty {
+            name: "tel".to_string(),
+            datatype: DataType::String,
+        }];
+        let result = analyze_with_properties(content.as_bytes(), properties, |_| {});
+        assert_eq!(
+            result["FieldNames"],
+            serde_json::json!(["email", "tel", "phone", ""])
+        );
+    }
+
+    #[test]
+    fn test_analyze_base64() {
+        let content = "email,name,age\njohn@example.com,John,30\njane@example.com,Jane,25\n";
+        let expected = analyze_content(content, |_| {});
+
+        let mut analyzer = CsvAnalyzer::with_properties(test_config(""), Vec::new());
+        let result: serde_json::Value =
+            serde_json::from_str(&analyzer.analyze_base64(&STANDARD.encode(content))).unwrap();
+        assert_eq!(result, expected);
+
+        let result: serde_json::Value =
+            serde_json::from_str(&analyzer.analyze_base64("not base64!")).unwrap();
+        assert_eq!(result["Error"], CsvErrorType::Process as u8);
+    }
+
+    #[test]
+    fn test_json_objects_output() {
+        let result = analyze_content(
+            "email,nick\njohn@example.com,Johnny\njane@example.com,Janie\n",
+            |config| config.output_format = OutputFormat::JsonObjects,
+        );
+        assert_eq!(
+            result["Data"],
+            serde_json::json!([
+                {"email": "john@example.com", "nick": "Johnny"},
+                {"email": "jane@example.com", "nick": "Janie"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ndjson_output() {
+        let content =
+            b"email,nick\njohn@example.com,Johnny\njane@example.com,Janie\nbob@example.com,Bobby\n";
+        let configure = |config: &mut Config| {
+            config.output_format = OutputFormat::Ndjson;
+            config.return_lines = 2;
+        };
+        let output = with_analyzer(content, Vec::new(), configure, CsvAnalyzer::analyze);
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(lines[0]).unwrap(),
+            serde_json::json!({"email": "john@example.com", "nick": "Johnny"})
+        );
+    }
+
+    #[test]
+    fn test_return_offset() {
+        let mut content = String::from("email,score\n");
+        for i in 0..30 {
+            content.push_str(&format!("user{}@example.com,{}\n", i, i));
+        }
+
+        let result = analyze_content(&content, |config| config.return_offset = 10);
+        let data = result["Data"].as_array().unwrap();
+        assert_eq!(data.len(), 10);
+        assert_eq!(data[0], serde_json::json!(["user10@example.com", "10"]));
+        assert_eq!(data[9], serde_json::json!(["user19@example.com", "19"]));
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1]));
+
+        // Past the sample: nothing left to return
+        let result = analyze_content(&content, |config| config.return_offset = 40);
+        assert!(result.get("Data").is_none());
+        assert_eq!(result["DataTypes"], serde_json::json!([0, 1]));
+
+        // Skipped rows are validated too
+        let content = content.replacen("user3@example.com", &"x".repeat(MAX_STRING_SIZE + 1), 1);
+        for offset in [0, 10] {
+            let result = analyze_content(&content, |config| config.return_offset = offset);
+            assert_eq!(result["Error"], CsvErrorType::ValueLong as u8);
+            assert_eq!(result["ErrorRow"], 5);
+        }
+    }
+
+    #[test]
+    fn test_suggested_locale() {
+        let result = analyze_content(
+            "email,joined,score\n\
+             john@example.com,12/31/2020,1.5\n\
+             jane@example.com,01/15/2021,2.25\n",
+            |_| {},
+        );
+        assert_eq!(result["SuggestedLocale"], "en_US");
+        assert_eq!(result["Locale"], "en_US");
+
+        let result = analyze_content(
+            "email;joined;score\n\
+             john@example.com;31/12/2020;1,5\n\
+             jane@example.com;15/01/2021;2,25\n",
+            |_| {},
+        );
+        assert_eq!(result["SuggestedLocale"], "fr_FR");
+        assert_eq!(result["Locale"], "en_US");
+
+        let result = analyze_content("email,name\njohn@example.com,John\n", |_| {});
+        assert!(result.get("SuggestedLocale").is_none());
+    }
+
+    #[test]
+    fn test_msgpack_output() {
+        let content = b"email,age\njohn@example.com,30\njane@example.com,25\n";
+        let mut bytes = Vec::new();
+        with_analyzer(
+            content,
+            Vec::new(),
+            |_| {},
+            |analyzer| analyzer.analyze_msgpack(&mut bytes),
+        )
+        .unwrap();
+        let decoded: SuccessResponse = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.header_names, vec!["email", "age"]);
+        assert_eq!(
+            decoded.data_types,
+            vec![DataType::String, DataType::Integer]
+        );
+        assert_eq!(decoded.data.unwrap()[1], vec!["jane@example.com", "25"]);
+
+        let decoded: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, analyze_bytes(content, |_| {}));
+    }
+
+    #[test]
+    fn test_pandas_dtypes_output() {
+        let result = analyze_content(
+            "email,age,score,active,joined\n\
+             john@example.com,30,1.5,true,2024-01-15\n\
+             jane@example.com,,2.25,,2024-02-20\n\
+             bob@example.com,41,,false,\n",
+            |config| config.output_format = OutputFormat::PandasDtypes,
+        );
+        // Nullable types, the age and active columns have empty values
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "email": "object",
+                "age": "Int64",
+                "score": "float64",
+                "active": "boolean",
+                "joined": "datetime64[ns]",
+            })
+        );
+    }
+
+    #[test]
+    fn test_detect_all() {
+        let report = detect_all(b"email;name\njohn@example.com;John\njane@example.com;Jane\n");
+        assert_eq!(
+            report.unwrap(),
+            DetectionReport {
+                charset: "ansi".to_string(),
+                text_delimiter: '"',
+                field_delimiter: ';',
+                has_header: true,
+                column_count: 2,
+            }
+        );
+
+        let report =
+            detect_all(b"'john@example.com'\t'Jos\xe9'\t30\n'jane@example.com'\t'Ren\xe9e'\t25\n")
+                .unwrap();
+        assert_eq!(report.charset, "cp1252");
+        assert_eq!(report.text_delimiter, '\'');
+        assert_eq!(report.field_delimiter, '\t');
+        assert!(!report.has_header);
+        assert_eq!(report.column_count, 3);
+    }
+
+    #[test]
+    fn test_detect_all_errors() {
+        assert!(matches!(
+            detect_all(b"\n\n"),
+            Err(CsvAnalyzerError::CsvError(CsvErrorType::Sample))
+        ));
+        assert!(matches!(
+            detect_all(&[0u8; 64]),
+            Err(CsvAnalyzerError::CsvError(CsvErrorType::Binary))
+        ));
+        assert!(crate::detection::detect_all(b"\n\n").is_err());
+    }
 }
@@ -1,17 +1,36 @@
+use crate::db::pgpass;
+use crate::detection::ColumnTypeDetector;
 use crate::error::{CsvAnalyzerError, Result};
+use crate::output::{Emit, OutputFormat};
+use crate::types::{DataType, DateOrder};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Database configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DbConfig {
     pub host: String,
     pub port: u16,
     pub database: String,
     pub user: String,
     pub password: String,
+    /// File holding the password, read at connection time when `password`
+    /// is empty
+    pub password_file: Option<PathBuf>,
+    /// Connection timeout in seconds, the OS default applies when unset
+    pub connect_timeout_secs: Option<u64>,
+    /// Number of connection retries after a failed attempt
+    pub connect_retries: u32,
+    /// Contact metadata table name, `{akid}` is replaced with the account id
+    /// (e.g. `t{akid}_contact_meta`). `contact_meta` when unset.
+    pub contact_meta_table: Option<String>,
+    /// SQLite file to read contact metadata from instead of PostgreSQL
+    pub sqlite_path: Option<PathBuf>,
 }
 
 impl DbConfig {
@@ -29,6 +48,7 @@ impl DbConfig {
             database: database.into(),
             user: user.into(),
             password: password.into(),
+            ..DbConfig::default()
         }
     }
 
@@ -56,9 +76,14 @@ impl DbConfig {
             CsvAnalyzerError::ConfigError("PGUSER environment variable not set".to_string())
         })?;
 
-        let password = env::var("PGPASSWORD").map_err(|_| {
-            CsvAnalyzerError::ConfigError("PGPASSWORD environment variable not set".to_string())
-        })?;
+        // Without PGPASSWORD the password is looked up in PGPASSFILE or
+        // ~/.pgpass at connection time
+        let password = env::var("PGPASSWORD").unwrap_or_default();
+
+        // Optional, same variable as libpq
+        let connect_timeout_secs = env::var("PGCONNECT_TIMEOUT")
+            .ok()
+            .and_then(|t| t.parse().ok());
 
         Ok(DbConfig {
             host,
@@ -66,17 +91,43 @@ impl DbConfig {
             database,
             user,
             password,
+            connect_timeout_secs,
+            ..DbConfig::default()
         })
     }
 
-    /// Load configuration from a config file (mailjet.conf format)
-    /// Reads from the [PGGLOBAL] section
+    /// Load configuration from a config file. `.toml` and `.json` files hold
+    /// the connection fields at the top level, anything else is read as
+    /// mailjet.conf from its [PGGLOBAL] section.
     /// Returns an error if required configuration values are missing
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(path.as_ref()).map_err(|e| {
             CsvAnalyzerError::ConfigError(format!("Failed to read config file: {}", e))
         })?;
 
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => toml::from_str::<DbConfigFile>(&content)
+                .map_err(|e| {
+                    CsvAnalyzerError::ConfigError(format!("Failed to parse TOML config: {}", e))
+                })?
+                .into_db_config(),
+            Some("json") => serde_json::from_str::<DbConfigFile>(&content)
+                .map_err(|e| {
+                    CsvAnalyzerError::ConfigError(format!("Failed to parse JSON config: {}", e))
+                })?
+                .into_db_config(),
+            _ => Self::from_ini(&content),
+        }
+    }
+
+    /// Parse the mailjet.conf format
+    fn from_ini(content: &str) -> Result<Self> {
         let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
         let mut current_section = String::new();
 
@@ -154,15 +205,28 @@ impl DbConfig {
             })?
             .clone();
 
-        let password = pgglobal
-            .get("PASSWORD")
+        // PASSWORDFILE can stand in for PASSWORD
+        let password_file = pgglobal
+            .get("PASSWORDFILE")
             .filter(|v| !v.is_empty())
-            .ok_or_else(|| {
-                CsvAnalyzerError::ConfigError(
+            .map(PathBuf::from);
+        let password = match pgglobal.get("PASSWORD").filter(|v| !v.is_empty()) {
+            Some(password) => password.clone(),
+            None if password_file.is_some() => String::new(),
+            None => {
+                return Err(CsvAnalyzerError::ConfigError(
                     "Missing or empty PASSWORD in [PGGLOBAL] section".to_string(),
-                )
-            })?
-            .clone();
+                ))
+            }
+        };
+
+        // Connection timeout and retries are optional
+        let connect_timeout_secs = pgglobal.get("CONNECTTIMEOUT").and_then(|t| t.parse().ok());
+        let connect_retries = pgglobal
+            .get("CONNECTRETRIES")
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(0);
+        let contact_meta_table = pgglobal.get("CONTACTMETATABLE").cloned();
 
         Ok(DbConfig {
             host,
@@ -170,47 +234,286 @@ impl DbConfig {
             database,
             user,
             password,
+            password_file,
+            connect_timeout_secs,
+            connect_retries,
+            contact_meta_table,
+            ..DbConfig::default()
         })
     }
 
-    /// Build PostgreSQL connection string
+    /// Build PostgreSQL connection string. An empty password is left out,
+    /// see `resolve_password`.
     pub fn connection_string(&self) -> String {
-        format!(
-            "host={} port={} dbname={} user={} password={}",
-            self.host, self.port, self.database, self.user, self.password
-        )
+        self.user_pool_connection_string(&self.host, &self.database)
     }
 
     /// Build connection string for a specific user pool
     pub fn user_pool_connection_string(&self, ip_rw: &str, pool_name: &str) -> String {
-        format!(
-            "host={} port={} dbname={} user={} password={}",
-            ip_rw, self.port, pool_name, self.user, self.password
-        )
+        let conn_str = format!(
+            "host={} port={} dbname={} user={}",
+            ip_rw, self.port, pool_name, self.user
+        );
+        if self.password.is_empty() {
+            conn_str
+        } else {
+            format!("{} password={}", conn_str, self.password)
+        }
+    }
+
+    /// Password to connect to `database` on `host` with: the configured
+    /// password, else the content of `password_file`, else the matching
+    /// `.pgpass` entry. None when none of them provides one.
+    pub fn resolve_password(&self, host: &str, database: &str) -> Result<Option<String>> {
+        if !self.password.is_empty() {
+            return Ok(Some(self.password.clone()));
+        }
+        if let Some(ref path) = self.password_file {
+            let password = fs::read_to_string(path).map_err(|e| {
+                CsvAnalyzerError::ConfigError(format!(
+                    "Failed to read password file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            return Ok(Some(password.trim_end_matches(['\r', '\n']).to_string()));
+        }
+        match pgpass::default_path() {
+            Some(path) => pgpass::lookup(&path, host, self.port, database, &self.user),
+            None => Ok(None),
+        }
     }
 }
 
-/// Application configuration
-#[derive(Debug, Clone)]
+/// Detected CSV format parameters.
+/// Obtained from an analysis and fed back through `Config::parse_profile`
+/// to parse similar files the same way without re-running detection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParseProfile {
+    pub charset: String,
+    pub field_delimiter: char,
+    pub text_delimiter: char,
+    pub skip_header: bool,
+    pub date_time_format: Option<String>,
+    /// Number of leading lines discarded before the header/data
+    pub skip_lines: usize,
+}
+
+/// Application configuration.
+/// Can be loaded from a TOML or JSON file; database settings are never read
+/// from it and keep coming from mailjet.conf, the environment or the CLI.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub akid: i64,
     pub locale: String,
     pub filename: String,
+    #[serde(skip)]
     pub db: DbConfig,
     pub scan_lines: usize,
+    /// Bytes read for the sample, whichever of this and `scan_lines` is
+    /// reached first
+    pub max_bytes: usize,
     pub return_lines: usize,
+    /// Data rows of the sample skipped before the returned ones, to preview
+    /// another part of the file; detection and value validation still use
+    /// the whole sample
+    pub return_offset: usize,
+    /// Leading lines (e.g. a report title) discarded before the header/data
+    pub skip_lines: usize,
+    /// Lines starting with this character, after optional whitespace, are
+    /// comments left out of the analysis
+    pub comment_prefix: Option<char>,
+    /// Escape character that makes the next character literal, delimiter or
+    /// quote, inside quoted fields too (e.g. `\` in MySQL's `a\;b`)
+    pub escape_char: Option<char>,
+    /// Previously detected format to apply instead of running detection
+    pub parse_profile: Option<ParseProfile>,
+    /// Rendering of a successful analysis
+    pub output_format: OutputFormat,
+    /// Render `DataTypes` in JSON as type names (e.g. `"Integer"`) instead
+    /// of the Pascal codes
+    pub type_names_as_strings: bool,
+    /// Regex replacing the built-in email validation
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub email_regex: Option<Regex>,
+    /// Accepted email top-level domains, any TLD when unset
+    pub allowed_tlds: Option<Vec<String>>,
+    /// Fail with EmailNotFound when no email column is detected
+    pub require_email: bool,
+    /// Convert full-width digits to ASCII before detecting numeric types
+    pub normalize_fullwidth: bool,
+    /// Date order reported for columns that fit several orders (e.g. `01/02/2020`)
+    pub prefer_date_order: Option<DateOrder>,
+    /// Truncate over-long headers and values and report them as warnings
+    /// instead of failing with ColumnLong/ValueLong
+    pub collect_warnings: bool,
+    /// Detect column types over the whole file instead of the sample.
+    /// Percent and currency columns are still recognized from the sample.
+    pub full_scan: bool,
+    /// Detect column types; when false every column is reported String,
+    /// skipping numeric, datetime, percent and currency detection (only
+    /// String contact properties then match)
+    pub infer_types: bool,
+    /// Fail with VariousFieldsCount unless every line has the dominant column count
+    pub strict_columns: bool,
+    /// Report rows of the sample repeating an earlier row
+    pub detect_duplicate_rows: bool,
+    /// Fail with an Encoding error on bytes invalid for the charset instead
+    /// of decoding them as replacement characters
+    pub strict_encoding: bool,
+    /// Fail with an Encoding error when the share (between 0 and 1) of
+    /// characters that could not be decoded exceeds this ratio, tolerating
+    /// a few bad bytes
+    pub max_decode_error_ratio: Option<f64>,
+    /// Name the email column after the property its header matches, instead of `email`
+    pub email_property_precedence: bool,
+    /// Map headers matching no property to the closest one within this edit
+    /// distance (e.g. `Frist Name` to `FirstName`), noted in `MatchNotes`
+    pub fuzzy_match_distance: Option<usize>,
+    /// Report a human-readable label of the charset (e.g. `Windows-1252`)
+    pub report_charset_label: bool,
+    /// Charset to decode the file with instead of detecting it (e.g. `cp1251`)
+    pub force_charset: Option<String>,
+    /// Treat the first row as header (`Some(true)`) or as data (`Some(false)`)
+    /// instead of detecting it
+    pub force_header: Option<bool>,
+    /// Column names of a file without header row, used for matching and
+    /// output instead of `Field1..N`. Their count must match the column count.
+    pub provided_headers: Option<Vec<String>>,
+    /// Remove NUL bytes from the decoded text, reporting the lines they were
+    /// on as warnings, instead of keeping them inside fields
+    pub strip_nul_bytes: bool,
+    /// Record the time spent in each analysis stage, see `CsvAnalyzer::timings`
+    pub profile_timings: bool,
+    /// Detect a units/metadata row (e.g. `kg,m`) after the header and skip it
+    /// instead of analyzing it as data
+    pub detect_units_row: bool,
+    /// Maximum length in bytes of a column name or field value
+    pub max_string_size: usize,
+    /// Characters allowed in header names besides letters and digits
+    /// (e.g. `_-`), any character when unset. A header with another one
+    /// fails with HeaderChar.
+    pub header_chars: Option<String>,
+    /// Maximum number of columns
+    pub max_columns: usize,
+    /// Maximum number of fields in any single row, checked while parsing so a
+    /// malformed row cannot allocate without bound
+    pub max_row_fields: usize,
+    /// Share of the sample lines (in percent) that must have the same column count
+    pub column_count_percent: usize,
+    /// Column count of the file, instead of the detected one. Shorter rows
+    /// are padded with empty values, longer ones truncated with a warning.
+    pub expected_columns: Option<usize>,
+    /// Unwrap Excel `="0123"` text values to `0123`, typing their column String
+    pub unwrap_excel_text: bool,
+    /// Remove control characters (e.g. a `\x1A` EOF marker) ending lines,
+    /// reporting them as warnings
+    pub strip_trailing_control: bool,
+    /// Drop the empty last column left by a delimiter ending most lines
+    /// (e.g. `a,b,c,`)
+    pub trim_trailing_empty_column: bool,
+    /// Share of the sample lines (in percent) that must contain a quote
+    /// character for it to be detected as the text separator
+    pub text_sep_percent: usize,
+    /// Print the detected profile, or the parsed sample as CSV
+    pub emit: Emit,
+    /// Expected data type of columns, by column number (1-based). A column
+    /// detected otherwise fails with TypeMismatch, or is reported as a
+    /// warning with `collect_warnings`.
+    pub expected_types: Vec<(usize, DataType)>,
+    /// Report per-column statistics (min/max, distinct values) of the sample
+    pub compute_stats: bool,
+    /// Report the empty (or missing) cells per column of the sample
+    pub report_null_counts: bool,
+    /// Don't print non-fatal warnings (e.g. database unavailable) to stderr
+    pub quiet: bool,
+    /// Estimate the total number of data rows of the file from the sample
+    pub estimate_rows: bool,
+    /// Report the column likely holding a unique identifier
+    pub detect_id_column: bool,
+    /// Report the text columns holding http(s) URLs (e.g. a website column)
+    pub detect_url_columns: bool,
+    /// Report missing values (empty and unquoted) as null in `Data`, keeping
+    /// explicitly quoted empty values as empty strings
+    pub null_for_missing: bool,
+    /// Custom detectors tried in order on each column before the built-in
+    /// type detection; a claimed column is reported as String with the
+    /// detector's type name in `SemanticTypes`, not in `FieldNames`
+    #[serde(skip)]
+    pub type_detectors: Vec<Arc<dyn ColumnTypeDetector>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            akid: 0,
+            locale: String::new(),
+            filename: String::new(),
+            db: DbConfig::default(),
+            scan_lines: crate::types::constants::MAX_SCAN_LINES,
+            max_bytes: crate::types::constants::MAX_BYTES,
+            return_lines: crate::types::constants::MAX_RETURN_LINES,
+            return_offset: 0,
+            skip_lines: 0,
+            comment_prefix: None,
+            escape_char: None,
+            parse_profile: None,
+            output_format: OutputFormat::default(),
+            type_names_as_strings: false,
+            email_regex: None,
+            allowed_tlds: None,
+            require_email: true,
+            infer_types: true,
+            normalize_fullwidth: false,
+            prefer_date_order: None,
+            collect_warnings: false,
+            full_scan: false,
+            strict_columns: false,
+            detect_duplicate_rows: false,
+            strict_encoding: false,
+            max_decode_error_ratio: None,
+            email_property_precedence: false,
+            fuzzy_match_distance: None,
+            report_charset_label: false,
+            force_charset: None,
+            force_header: None,
+            provided_headers: None,
+            strip_nul_bytes: false,
+            profile_timings: false,
+            detect_units_row: false,
+            max_string_size: crate::types::constants::MAX_STRING_SIZE,
+            header_chars: None,
+            max_columns: crate::types::constants::MAX_COLUMNS,
+            max_row_fields: crate::types::constants::MAX_ROW_FIELDS,
+            column_count_percent: crate::types::constants::COLUMN_COUNT_PERCENT,
+            expected_columns: None,
+            trim_trailing_empty_column: false,
+            strip_trailing_control: false,
+            unwrap_excel_text: false,
+            text_sep_percent: crate::types::constants::TEXT_SEP_PERCENT,
+            emit: Emit::default(),
+            expected_types: Vec::new(),
+            compute_stats: false,
+            report_null_counts: false,
+            quiet: false,
+            estimate_rows: false,
+            detect_id_column: false,
+            detect_url_columns: false,
+            null_for_missing: false,
+            type_detectors: Vec::new(),
+        }
+    }
 }
 
 impl Config {
     pub fn new(akid: i64, locale: impl Into<String>, filename: impl Into<String>) -> Result<Self> {
-        Ok(Config {
+        Ok(Self::new_with_db(
             akid,
-            locale: locale.into(),
-            filename: filename.into(),
-            db: DbConfig::from_env()?,
-            scan_lines: crate::types::constants::MAX_SCAN_LINES,
-            return_lines: crate::types::constants::MAX_RETURN_LINES,
-        })
+            locale,
+            filename,
+            DbConfig::from_env()?,
+        ))
     }
 
     pub fn new_with_db(
@@ -224,17 +527,102 @@ impl Config {
             locale: locale.into(),
             filename: filename.into(),
             db,
-            scan_lines: crate::types::constants::MAX_SCAN_LINES,
-            return_lines: crate::types::constants::MAX_RETURN_LINES,
+            ..Config::default()
         }
     }
 
+    /// Load analyzer options from a TOML file
+    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = read_options_file(path.as_ref())?;
+        toml::from_str(&content).map_err(|e| {
+            CsvAnalyzerError::ConfigError(format!("Failed to parse TOML options: {}", e))
+        })
+    }
+
+    /// Load analyzer options from a JSON file
+    pub fn from_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = read_options_file(path.as_ref())?;
+        serde_json::from_str(&content).map_err(|e| {
+            CsvAnalyzerError::ConfigError(format!("Failed to parse JSON options: {}", e))
+        })
+    }
+
     pub fn with_db_config(mut self, db: DbConfig) -> Self {
         self.db = db;
         self
     }
 }
 
+fn deserialize_regex<'de, D>(deserializer: D) -> std::result::Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|pattern| Regex::new(&pattern).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Database configuration as written in a TOML or JSON config file
+#[derive(Debug, Deserialize)]
+struct DbConfigFile {
+    host: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    database: String,
+    user: String,
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    password_file: Option<PathBuf>,
+    #[serde(default)]
+    connect_timeout_secs: Option<u64>,
+    #[serde(default)]
+    connect_retries: u32,
+    #[serde(default)]
+    contact_meta_table: Option<String>,
+}
+
+fn default_port() -> u16 {
+    5432
+}
+
+impl DbConfigFile {
+    fn into_db_config(self) -> Result<DbConfig> {
+        let password_required = self.password_file.is_none();
+        for (name, value, required) in [
+            ("host", &self.host, true),
+            ("database", &self.database, true),
+            ("user", &self.user, true),
+            ("password", &self.password, password_required),
+        ] {
+            if required && value.is_empty() {
+                return Err(CsvAnalyzerError::ConfigError(format!(
+                    "Missing or empty {} in config file",
+                    name
+                )));
+            }
+        }
+
+        Ok(DbConfig {
+            host: self.host,
+            port: self.port,
+            database: self.database,
+            user: self.user,
+            password: self.password,
+            password_file: self.password_file,
+            connect_timeout_secs: self.connect_timeout_secs,
+            connect_retries: self.connect_retries,
+            contact_meta_table: self.contact_meta_table,
+            ..DbConfig::default()
+        })
+    }
+}
+
+fn read_options_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path)
+        .map_err(|e| CsvAnalyzerError::ConfigError(format!("Failed to read options file: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -293,6 +681,32 @@ PASSWORD=test_pass
         let db_config = DbConfig::from_file(&config_path).unwrap();
 
         assert_eq!(db_config.port, 5433);
+        assert_eq!(db_config.connect_timeout_secs, None);
+        assert_eq!(db_config.connect_retries, 0);
+
+        // Clean up
+        std::fs::remove_file(config_path).ok();
+    }
+
+    #[test]
+    fn test_parse_config_file_with_timeout() {
+        let config_content = r#"[PGGLOBAL]
+USERNAME=test_user
+HOSTNAME=test.example.com
+DATABASENAME=test_db
+PASSWORD=test_pass
+CONNECTTIMEOUT=5
+CONNECTRETRIES=2
+"#;
+
+        let temp_dir = std::env::temp_dir();
+        let config_path = temp_dir.join("test_mailjet_timeout.conf");
+        std::fs::write(&config_path, config_content).unwrap();
+
+        let db_config = DbConfig::from_file(&config_path).unwrap();
+
+        assert_eq!(db_config.connect_timeout_secs, Some(5));
+        assert_eq!(db_config.connect_retries, 2);
 
         // Clean up
         std::fs::remove_file(config_path).ok();
@@ -360,4 +774,171 @@ PASSWORD=test_pass
         // Clean up
         std::fs::remove_file(config_path).ok();
     }
+
+    #[test]
+    fn test_config_from_toml() {
+        let options_content = r#"scan_lines = 200
+return_lines = 5
+require_email = false
+escape_char = "\\"
+"#;
+
+        let temp_dir = std::env::temp_dir();
+        let options_path = temp_dir.join("test_csvanalyzer_options.toml");
+        std::fs::write(&options_path, options_content).unwrap();
+
+        let config = Config::from_toml(&options_path).unwrap();
+
+        assert_eq!(config.scan_lines, 200);
+        assert_eq!(config.return_lines, 5);
+        assert!(!config.require_email);
+        assert_eq!(config.escape_char, Some('\\'));
+        assert!(config.parse_profile.is_none());
+        assert!(config.email_regex.is_none());
+
+        // Clean up
+        std::fs::remove_file(options_path).ok();
+    }
+
+    #[test]
+    fn test_config_from_json() {
+        let options_content = r#"{"scan_lines": 50, "email_regex": "@corp\\.example$"}"#;
+
+        let temp_dir = std::env::temp_dir();
+        let options_path = temp_dir.join("test_csvanalyzer_options.json");
+        std::fs::write(&options_path, options_content).unwrap();
+
+        let config = Config::from_json(&options_path).unwrap();
+
+        assert_eq!(config.scan_lines, 50);
+        assert_eq!(
+            config.return_lines,
+            crate::types::constants::MAX_RETURN_LINES
+        );
+        assert!(config.email_regex.unwrap().is_match("john@corp.example"));
+
+        // Clean up
+        std::fs::remove_file(options_path).ok();
+    }
+
+    #[test]
+    fn test_parse_config_file_toml() {
+        let config_content = r#"host = "test.example.com"
+port = 5433
+database = "test_db"
+user = "test_user"
+password = "test_pass"
+connect_timeout_secs = 5
+"#;
+
+        let temp_dir = std::env::temp_dir();
+        let config_path = temp_dir.join("test_csvanalyzer_db.toml");
+        std::fs::write(&config_path, config_content).unwrap();
+
+        let db_config = DbConfig::from_file(&config_path).unwrap();
+
+        assert_eq!(db_config.host, "test.example.com");
+        assert_eq!(db_config.port, 5433);
+        assert_eq!(db_config.database, "test_db");
+        assert_eq!(db_config.user, "test_user");
+        assert_eq!(db_config.password, "test_pass");
+        assert_eq!(db_config.connect_timeout_secs, Some(5));
+        assert_eq!(db_config.connect_retries, 0);
+
+        // Clean up
+        std::fs::remove_file(config_path).ok();
+    }
+
+    #[test]
+    fn test_parse_config_file_toml_missing_field() {
+        let config_content = r#"host = "test.example.com"
+database = "test_db"
+user = "test_user"
+"#;
+
+        let temp_dir = std::env::temp_dir();
+        let config_path = temp_dir.join("test_csvanalyzer_db_missing.toml");
+        std::fs::write(&config_path, config_content).unwrap();
+
+        let result = DbConfig::from_file(&config_path);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("password"));
+
+        // Clean up
+        std::fs::remove_file(config_path).ok();
+    }
+
+    #[test]
+    fn test_parse_config_file_json() {
+        let config_content = r#"{"host": "test.example.com", "database": "test_db",
+            "user": "test_user", "password": "test_pass"}"#;
+
+        let temp_dir = std::env::temp_dir();
+        let config_path = temp_dir.join("test_csvanalyzer_db.json");
+        std::fs::write(&config_path, config_content).unwrap();
+
+        let db_config = DbConfig::from_file(&config_path).unwrap();
+
+        assert_eq!(db_config.host, "test.example.com");
+        assert_eq!(db_config.port, 5432); // Default port
+
+        // Clean up
+        std::fs::remove_file(config_path).ok();
+    }
+
+    #[test]
+    fn test_parse_config_file_password_file() {
+        let password_path = std::env::temp_dir().join("test_csvanalyzer_db_password");
+        std::fs::write(&password_path, "file_pass\n").unwrap();
+        let config_content = format!(
+            "[PGGLOBAL]\nUSERNAME=test_user\nHOSTNAME=test.example.com\n\
+             DATABASENAME=test_db\nPASSWORDFILE={}\n",
+            password_path.display()
+        );
+
+        let config_path = std::env::temp_dir().join("test_mailjet_password_file.conf");
+        std::fs::write(&config_path, config_content).unwrap();
+
+        let db_config = DbConfig::from_file(&config_path).unwrap();
+
+        assert_eq!(db_config.password, "");
+        assert!(!db_config.connection_string().contains("password="));
+        assert_eq!(
+            db_config
+                .resolve_password("test.example.com", "test_db")
+                .unwrap()
+                .as_deref(),
+            Some("file_pass")
+        );
+
+        // Clean up
+        std::fs::remove_file(config_path).ok();
+        std::fs::remove_file(password_path).ok();
+    }
+
+    #[test]
+    fn test_parse_config_file_toml_password_file() {
+        let config_content = r#"host = "test.example.com"
+database = "test_db"
+user = "test_user"
+password_file = "/nonexistent/db_password"
+"#;
+
+        let config_path = std::env::temp_dir().join("test_csvanalyzer_db_password_file.toml");
+        std::fs::write(&config_path, config_content).unwrap();
+
+        let db_config = DbConfig::from_file(&config_path).unwrap();
+
+        // Read at connection time, not when loading the config
+        assert_eq!(
+            db_config.password_file,
+            Some(PathBuf::from("/nonexistent/db_password"))
+        );
+        assert!(db_config
+            .resolve_password("test.example.com", "test_db")
+            .is_err());
+
+        // Clean up
+        std::fs::remove_file(config_path).ok();
+    }
 }
@@ -0,0 +1,529 @@
+use crate::config::DbConfig;
+use crate::debug_trace;
+use crate::error::{CsvAnalyzerError, Result};
+use crate::types::{ContactProperty, DataType};
+use postgres::{Client, NoTls};
+use std::thread;
+use std::time::Duration;
+
+pub mod pgpass;
+pub mod sqlite;
+
+pub use sqlite::SqliteMetadataSource;
+
+/// Contact metadata table used when `DbConfig::contact_meta_table` is unset
+const DEFAULT_CONTACT_META_TABLE: &str = "contact_meta";
+
+/// Characters of a name per edit a fuzzy property match may differ by
+const FUZZY_CHARS_PER_EDIT: usize = 3;
+
+/// Delay before the first connection retry, doubled for each further retry
+const RETRY_BACKOFF_MS: u64 = 200;
+
+/// Doublings of the retry delay, capping it at 12.8s
+const MAX_BACKOFF_DOUBLINGS: u32 = 6;
+
+/// Delay before the connection retry following `attempt` failed ones
+fn retry_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(RETRY_BACKOFF_MS << attempt.min(MAX_BACKOFF_DOUBLINGS))
+}
+
+/// Source of the contact properties an account has defined
+pub trait MetadataSource {
+    /// Query contact metadata for an account
+    fn get_contact_properties(&mut self, akid: i64) -> Result<Vec<ContactProperty>>;
+}
+
+/// Create the metadata source selected by the configuration:
+/// SQLite when `sqlite_path` is set, PostgreSQL otherwise
+pub fn metadata_source(config: &DbConfig) -> Box<dyn MetadataSource> {
+    match config.sqlite_path {
+        Some(ref path) => Box::new(SqliteMetadataSource::new(
+            path,
+            config.contact_meta_table.clone(),
+        )),
+        None => Box::new(DbConnection::new(config.clone())),
+    }
+}
+
+/// PostgreSQL connection manager
+pub struct DbConnection {
+    global_client: Option<Client>,
+    user_client: Option<Client>,
+    config: DbConfig,
+}
+
+/// Pool info from app table
+#[derive(Debug)]
+pub struct PoolInfo {
+    pub pool: i32,
+    pub ip_rw: String,
+    pub db_version: i32,
+}
+
+impl DbConnection {
+    pub fn new(config: DbConfig) -> Self {
+        DbConnection {
+            global_client: None,
+            user_client: None,
+            config,
+        }
+    }
+
+    /// Connect to global database
+    pub fn connect_global(&mut self) -> Result<()> {
+        let conn_str = self.config.connection_string();
+        let password = self
+            .config
+            .resolve_password(&self.config.host, &self.config.database)?;
+        let client = self.connect(&conn_str, password).map_err(|e| {
+            CsvAnalyzerError::DatabaseError(format!("Failed to connect to global database: {}", e))
+        })?;
+        self.global_client = Some(client);
+        Ok(())
+    }
+
+    /// Connect with the configured timeout, retrying with a backoff.
+    /// Returns the last error once all retries are exhausted.
+    fn connect(
+        &self,
+        conn_str: &str,
+        password: Option<String>,
+    ) -> std::result::Result<Client, postgres::Error> {
+        let mut pg_config: postgres::Config = conn_str.parse()?;
+        if let Some(password) = password {
+            pg_config.password(password);
+        }
+        if let Some(secs) = self.config.connect_timeout_secs {
+            pg_config.connect_timeout(Duration::from_secs(secs));
+        }
+
+        let mut attempt = 0;
+        loop {
+            match pg_config.connect(NoTls) {
+                Ok(client) => return Ok(client),
+                Err(_) if attempt < self.config.connect_retries => {
+                    thread::sleep(retry_backoff(attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Query app table for pool info
+    pub fn get_pool_info(&mut self, akid: i64) -> Result<PoolInfo> {
+        let client = self.global_client.as_mut().ok_or_else(|| {
+            CsvAnalyzerError::DatabaseError("Global connection not established".to_string())
+        })?;
+
+        let sql = format!(
+            "SELECT pool, ip_rw, db_version FROM app WHERE id = {}",
+            akid
+        );
+
+        // Use simple_query to avoid prepared statement issues with PgBouncer
+        let messages = client.simple_query(&sql).map_err(|e| {
+            CsvAnalyzerError::DatabaseError(format!("Failed to query app table: {}", e))
+        })?;
+
+        // Find the first Row message and extract data
+        for message in messages {
+            if let postgres::SimpleQueryMessage::Row(row) = message {
+                // row.get() returns Option<&str>
+                let pool_str = row.get(0).ok_or_else(|| {
+                    CsvAnalyzerError::DatabaseError("Column 0 (pool) is NULL".to_string())
+                })?;
+                let pool: i32 = pool_str.parse().map_err(|_| {
+                    CsvAnalyzerError::DatabaseError(format!(
+                        "Failed to parse pool value: '{}'",
+                        pool_str
+                    ))
+                })?;
+
+                let ip_rw = row
+                    .get(1)
+                    .ok_or_else(|| {
+                        CsvAnalyzerError::DatabaseError("Column 1 (ip_rw) is NULL".to_string())
+                    })?
+                    .to_string();
+
+                let db_version_str = row.get(2).ok_or_else(|| {
+                    CsvAnalyzerError::DatabaseError("Column 2 (db_version) is NULL".to_string())
+                })?;
+                let db_version: i32 = db_version_str.parse().map_err(|_| {
+                    CsvAnalyzerError::DatabaseError(format!(
+                        "Failed to parse db_version value: '{}'",
+                        db_version_str
+                    ))
+                })?;
+
+                return Ok(PoolInfo {
+                    pool,
+                    ip_rw,
+                    db_version,
+                });
+            }
+        }
+
+        Err(CsvAnalyzerError::DatabaseError(
+            "No rows returned from app table query".to_string(),
+        ))
+    }
+
+    /// Connect to user pool database
+    pub fn connect_user_pool(&mut self, pool_info: &PoolInfo) -> Result<()> {
+        // Format the pool number as a database name with 7-digit zero padding
+        let pool_name = format!("p{:07}", pool_info.pool);
+        let conn_str = self
+            .config
+            .user_pool_connection_string(&pool_info.ip_rw, &pool_name);
+        let password = self.config.resolve_password(&pool_info.ip_rw, &pool_name)?;
+
+        let client = self.connect(&conn_str, password).map_err(|e| {
+            CsvAnalyzerError::DatabaseError(format!("Failed to connect to user pool: {}", e))
+        })?;
+        self.user_client = Some(client);
+        Ok(())
+    }
+
+    /// Query contact metadata for an account on the connected user pool
+    pub fn query_contact_properties(&mut self, akid: i64) -> Result<Vec<ContactProperty>> {
+        let sql = contact_meta_query(self.config.contact_meta_table.as_deref(), akid)?;
+        debug_trace!("Contact metadata query: {}", sql);
+        let client = self.user_client.as_mut().ok_or_else(|| {
+            CsvAnalyzerError::DatabaseError("User pool connection not established".to_string())
+        })?;
+
+        // Use simple_query to avoid prepared statement issues with PgBouncer
+        let messages = client.simple_query(&sql).map_err(|e| {
+            CsvAnalyzerError::DatabaseError(format!("Failed to query contact_meta: {}", e))
+        })?;
+
+        let mut properties = Vec::new();
+        let mut row_count = 0;
+
+        // Iterate through messages and process Row variants
+        for message in messages {
+            if let postgres::SimpleQueryMessage::Row(row) = message {
+                // row.get() returns Option<&str>
+                let name = row
+                    .get(0)
+                    .ok_or_else(|| {
+                        CsvAnalyzerError::DatabaseError(format!(
+                            "Column 0 (name) is NULL at row {}",
+                            row_count
+                        ))
+                    })?
+                    .to_string();
+
+                let datatype_str = row.get(1).ok_or_else(|| {
+                    CsvAnalyzerError::DatabaseError(format!(
+                        "Column 1 (datatype) is NULL at row {}",
+                        row_count
+                    ))
+                })?;
+
+                let datatype_int: i32 = datatype_str.parse().map_err(|_| {
+                    CsvAnalyzerError::DatabaseError(format!(
+                        "Failed to parse datatype value '{}' at row {}",
+                        datatype_str, row_count
+                    ))
+                })?;
+
+                let datatype = meta_datatype(datatype_int);
+
+                debug_trace!(
+                    "Parsed property {} - name: {}, datatype: {:?}",
+                    row_count,
+                    name,
+                    datatype
+                );
+                properties.push(ContactProperty { name, datatype });
+                row_count += 1;
+            }
+        }
+
+        Ok(properties)
+    }
+
+    /// Disconnect from all databases
+    pub fn disconnect(&mut self) {
+        self.global_client = None;
+        self.user_client = None;
+    }
+}
+
+impl MetadataSource for DbConnection {
+    /// Connect to the global database, then to the account's user pool
+    fn get_contact_properties(&mut self, akid: i64) -> Result<Vec<ContactProperty>> {
+        debug_trace!(
+            "Connecting to global database {}:{}/{}",
+            self.config.host,
+            self.config.port,
+            self.config.database
+        );
+        self.connect_global()?;
+        let pool_info = self.get_pool_info(akid)?;
+        debug_trace!("Account {} pool info: {:?}", akid, pool_info);
+        self.connect_user_pool(&pool_info)?;
+        self.query_contact_properties(akid)
+    }
+}
+
+impl Drop for DbConnection {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+/// Map a contact_meta datatype code to a DataType, String when unknown
+fn meta_datatype(code: i32) -> DataType {
+    match code {
+        0 => DataType::String,
+        1 => DataType::Integer,
+        2 => DataType::Float,
+        3 => DataType::Boolean,
+        4 => DataType::DateTime,
+        _ => DataType::String,
+    }
+}
+
+/// Build the contact metadata query for an account.
+/// The table name is interpolated into the SQL, so the akid must be positive
+/// and the resulting name a plain identifier.
+pub fn contact_meta_query(table_pattern: Option<&str>, akid: i64) -> Result<String> {
+    let pattern = table_pattern.unwrap_or(DEFAULT_CONTACT_META_TABLE);
+    if pattern.contains("{akid}") && akid <= 0 {
+        return Err(CsvAnalyzerError::ConfigError(format!(
+            "Invalid account id for contact_meta table: {}",
+            akid
+        )));
+    }
+
+    let table = pattern.replace("{akid}", &akid.to_string());
+    let valid = table
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(CsvAnalyzerError::ConfigError(format!(
+            "Invalid contact_meta table name: '{}'",
+            table
+        )));
+    }
+
+    // mnStatic = 0 (static namespace for contact properties)
+    Ok(format!(
+        "SELECT name, datatype FROM {} WHERE namespace = 0",
+        table
+    ))
+}
+
+/// Match a header name against known contact properties, ignoring case,
+/// then also spaces, underscores and hyphens (`First Name` and `first_name`
+/// match `FirstName`)
+pub fn match_property<'a>(
+    header: &str,
+    properties: &'a [ContactProperty],
+) -> Option<&'a ContactProperty> {
+    let header_lower = header.to_lowercase();
+    let header_normalized = normalize_property_name(header);
+    properties
+        .iter()
+        .find(|p| p.name.to_lowercase() == header_lower)
+        .or_else(|| {
+            properties
+                .iter()
+                .find(|p| normalize_property_name(&p.name) == header_normalized)
+        })
+}
+
+/// Closest property to a header that `match_property` missed, e.g. a typo,
+/// with its edit distance between the normalized names, at most `max_distance`
+/// and one edit per FUZZY_CHARS_PER_EDIT characters of the shorter name, so
+/// that short headers like `id` don't match any short property
+pub fn fuzzy_match_property<'a>(
+    header: &str,
+    properties: &'a [ContactProperty],
+    max_distance: usize,
+) -> Option<(&'a ContactProperty, usize)> {
+    let header_normalized = normalize_property_name(header);
+    if header_normalized.is_empty() {
+        return None;
+    }
+    properties
+        .iter()
+        .filter_map(|p| {
+            let name = normalize_property_name(&p.name);
+            let shorter = header_normalized.chars().count().min(name.chars().count());
+            let distance = levenshtein(&header_normalized, &name);
+            (distance <= max_distance && distance * FUZZY_CHARS_PER_EDIT <= shorter)
+                .then_some((p, distance))
+        })
+        .min_by_key(|&(_, distance)| distance)
+}
+
+/// Lowercase a name, dropping spaces, underscores and hyphens
+fn normalize_property_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Edit distance (insertions, deletions, substitutions) between two strings
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_contact_meta_query() {
+        assert_eq!(
+            contact_meta_query(None, 42).unwrap(),
+            "SELECT name, datatype FROM contact_meta WHERE namespace = 0"
+        );
+        assert_eq!(
+            contact_meta_query(Some("t{akid}_contact_meta"), 42).unwrap(),
+            "SELECT name, datatype FROM t42_contact_meta WHERE namespace = 0"
+        );
+        assert!(contact_meta_query(Some("t{akid}_contact_meta"), -1).is_err());
+        assert!(contact_meta_query(Some("t{akid}_contact_meta"), 0).is_err());
+        assert!(contact_meta_query(Some("contact_meta; DROP TABLE app"), 42).is_err());
+        assert!(contact_meta_query(Some(""), 42).is_err());
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        assert_eq!(retry_backoff(0), Duration::from_millis(200));
+        assert_eq!(retry_backoff(2), Duration::from_millis(800));
+        assert_eq!(retry_backoff(6), Duration::from_millis(12_800));
+        assert_eq!(retry_backoff(20), Duration::from_millis(12_800));
+        assert_eq!(retry_backoff(u32::MAX), Duration::from_millis(12_800));
+    }
+
+    #[test]
+    fn test_connect_timeout_unroutable() {
+        // Non-routable address: the connection can only end through the timeout
+        let mut config = DbConfig::new("10.255.255.1", 5432, "test", "test", "test");
+        config.connect_timeout_secs = Some(1);
+        config.connect_retries = 1;
+
+        let mut db = DbConnection::new(config);
+        let start = Instant::now();
+        let result = db.connect_global();
+
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Failed to connect to global database"));
+        // Two attempts of at most 1s each plus the backoff
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_match_property() {
+        let properties = vec![
+            ContactProperty {
+                name: "email".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "FirstName".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "Age".to_string(),
+                datatype: DataType::Integer,
+            },
+        ];
+
+        // Exact match (case-insensitive)
+        let matched = match_property("firstname", &properties);
+        assert!(matched.is_some());
+        assert_eq!(matched.unwrap().name, "FirstName");
+
+        // Spaces, underscores and hyphens are ignored
+        for header in [
+            "First Name",
+            "first_name",
+            "first-name",
+            "firstName",
+            " FIRST_NAME ",
+        ] {
+            assert_eq!(
+                match_property(header, &properties).unwrap().name,
+                "FirstName"
+            );
+        }
+
+        // No match
+        let not_matched = match_property("unknown", &properties);
+        assert!(not_matched.is_none());
+        assert!(match_property("first.name", &properties).is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_match_property() {
+        let properties = vec![
+            ContactProperty {
+                name: "FirstName".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "LastName".to_string(),
+                datatype: DataType::String,
+            },
+        ];
+
+        let (prop, distance) = fuzzy_match_property("Frist Name", &properties, 2).unwrap();
+        assert_eq!((prop.name.as_str(), distance), ("FirstName", 2));
+        let (prop, distance) = fuzzy_match_property("last_nam", &properties, 2).unwrap();
+        assert_eq!((prop.name.as_str(), distance), ("LastName", 1));
+        assert!(fuzzy_match_property("Frist Name", &properties, 1).is_none());
+        assert!(fuzzy_match_property("city", &properties, 2).is_none());
+        assert!(fuzzy_match_property("__", &properties, 9).is_none());
+
+        // Short names need proportionally closer headers
+        let properties = vec![
+            ContactProperty {
+                name: "Name".to_string(),
+                datatype: DataType::String,
+            },
+            ContactProperty {
+                name: "Zip".to_string(),
+                datatype: DataType::String,
+            },
+        ];
+        assert!(fuzzy_match_property("id", &properties, 3).is_none());
+        assert!(fuzzy_match_property("age", &properties, 3).is_none());
+        assert!(fuzzy_match_property("Nmae", &properties, 3).is_none());
+        let (prop, distance) = fuzzy_match_property("Nme", &properties, 3).unwrap();
+        assert_eq!((prop.name.as_str(), distance), ("Name", 1));
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("firstname", "firstname"), 0);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::debug_trace;
+use crate::error::{CsvAnalyzerError, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Password file looked up when no password is configured: `PGPASSFILE`,
+/// or `~/.pgpass` like libpq
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("PGPASSFILE")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".pgpass")))
+}
+
+/// Find the password for a connection in a `.pgpass` file, made of
+/// `hostname:port:database:username:password` lines where `*` matches any
+/// value. The first matching line wins. A missing file, or one readable by
+/// group or others, gives no password.
+pub fn lookup(
+    path: &Path,
+    host: &str,
+    port: u16,
+    database: &str,
+    user: &str,
+) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    if !has_private_permissions(path)? {
+        debug_trace!(
+            "Ignoring password file {}: permissions should be 0600 or less",
+            path.display()
+        );
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| {
+        CsvAnalyzerError::ConfigError(format!(
+            "Failed to read password file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let port = port.to_string();
+    let wanted = [host, port.as_str(), database, user];
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(split_entry)
+        .find(|fields| {
+            fields.len() == 5
+                && fields
+                    .iter()
+                    .zip(wanted)
+                    .all(|(field, value)| field == "*" || field == value)
+        })
+        .map(|mut fields| fields.swap_remove(4)))
+}
+
+/// Split a `.pgpass` line on `:`, where `\:` and `\\` stand for the
+/// character itself. The password takes the rest of the line.
+fn split_entry(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields
+                .last_mut()
+                .unwrap()
+                .extend(chars.next().or(Some('\\'))),
+            ':' if fields.len() < 5 => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(unix)]
+fn has_private_permissions(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = fs::metadata(path).map_err(|e| {
+        CsvAnalyzerError::ConfigError(format!(
+            "Failed to read password file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(metadata.permissions().mode() & 0o077 == 0)
+}
+
+#[cfg(not(unix))]
+fn has_private_permissions(_path: &Path) -> Result<bool> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn pgpass_file(content: &str, mode: u32) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(file.path(), fs::Permissions::from_mode(mode)).unwrap();
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        file
+    }
+
+    #[test]
+    fn test_lookup_first_match() {
+        let file = pgpass_file(
+            "# comment\n\
+             db.example.com:5432:global:app:global_pass\n\
+             *:5432:*:app:pool_pass\n\
+             *:*:*:*:fallback\n",
+            0o600,
+        );
+
+        let lookup = |host, port, database, user| {
+            super::lookup(file.path(), host, port, database, user).unwrap()
+        };
+        assert_eq!(
+            lookup("db.example.com", 5432, "global", "app").as_deref(),
+            Some("global_pass")
+        );
+        assert_eq!(
+            lookup("10.0.0.1", 5432, "p0000042", "app").as_deref(),
+            Some("pool_pass")
+        );
+        assert_eq!(
+            lookup("10.0.0.1", 5433, "p0000042", "app").as_deref(),
+            Some("fallback")
+        );
+    }
+
+    #[test]
+    fn test_lookup_escapes() {
+        let file = pgpass_file(r"host\:1:5432:db:us\\er:pa:ss\\", 0o600);
+        assert_eq!(
+            lookup(file.path(), "host:1", 5432, "db", r"us\er")
+                .unwrap()
+                .as_deref(),
+            Some(r"pa:ss\")
+        );
+        assert_eq!(
+            lookup(file.path(), "host", 5432, "db", "user").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_lookup_missing_file() {
+        let path = Path::new("/nonexistent/.pgpass");
+        assert_eq!(lookup(path, "host", 5432, "db", "user").unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lookup_ignores_group_readable_file() {
+        let file = pgpass_file("*:*:*:*:secret\n", 0o640);
+        assert_eq!(
+            lookup(file.path(), "host", 5432, "db", "user").unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use super::{contact_meta_query, meta_datatype, MetadataSource};
+use crate::debug_trace;
+use crate::error::{CsvAnalyzerError, Result};
+use crate::types::ContactProperty;
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+
+/// Contact metadata read from a local SQLite file, with the same
+/// `contact_meta` layout as the PostgreSQL user pools
+pub struct SqliteMetadataSource {
+    path: PathBuf,
+    contact_meta_table: Option<String>,
+}
+
+impl SqliteMetadataSource {
+    pub fn new(path: impl AsRef<Path>, contact_meta_table: Option<String>) -> Self {
+        SqliteMetadataSource {
+            path: path.as_ref().to_path_buf(),
+            contact_meta_table,
+        }
+    }
+}
+
+impl MetadataSource for SqliteMetadataSource {
+    fn get_contact_properties(&mut self, akid: i64) -> Result<Vec<ContactProperty>> {
+        let sql = contact_meta_query(self.contact_meta_table.as_deref(), akid)?;
+        debug_trace!("Contact metadata query on {}: {}", self.path.display(), sql);
+
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| {
+                CsvAnalyzerError::DatabaseError(format!(
+                    "Failed to open SQLite database {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })?;
+
+        let mut stmt = conn.prepare(&sql).map_err(|e| {
+            CsvAnalyzerError::DatabaseError(format!("Failed to query contact_meta: {}", e))
+        })?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(ContactProperty {
+                    name: row.get(0)?,
+                    datatype: meta_datatype(row.get(1)?),
+                })
+            })
+            .map_err(|e| {
+                CsvAnalyzerError::DatabaseError(format!("Failed to query contact_meta: {}", e))
+            })?;
+
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| {
+                CsvAnalyzerError::DatabaseError(format!("Failed to read contact_meta row: {}", e))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataType;
+
+    #[test]
+    fn test_sqlite_contact_properties() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let conn = Connection::open(file.path()).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t42_contact_meta (name TEXT, datatype INTEGER, namespace INTEGER);
+             INSERT INTO t42_contact_meta VALUES ('nick', 0, 0), ('score', 1, 0), ('hidden', 2, 1);",
+        )
+        .unwrap();
+
+        let mut source =
+            SqliteMetadataSource::new(file.path(), Some("t{akid}_contact_meta".to_string()));
+        let props = source.get_contact_properties(42).unwrap();
+
+        assert_eq!(props.len(), 2);
+        assert_eq!(props[0].name, "nick");
+        assert_eq!(props[0].datatype, DataType::String);
+        assert_eq!(props[1].name, "score");
+        assert_eq!(props[1].datatype, DataType::Integer);
+    }
+
+    #[test]
+    fn test_sqlite_missing_table() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        Connection::open(file.path()).unwrap();
+
+        let mut source = SqliteMetadataSource::new(file.path(), None);
+        assert!(source.get_contact_properties(42).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Runtime switch for the detection and database traces on stderr
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable debug traces
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether debug traces are enabled
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Print a `DEBUG:` line to stderr when debug traces are enabled
+#[macro_export]
+macro_rules! debug_trace {
+    ($($arg:tt)*) => {
+        if $crate::debug::enabled() {
+            eprintln!("DEBUG: {}", format_args!($($arg)*));
+        }
+    };
+}
@@ -1,12 +1,21 @@
-use crate::types::constants::CSVA_GUESS_SIZE;
+use crate::types::constants::BUFF_SIZE;
 use chardetng::EncodingDetector;
-use encoding_rs::Encoding;
+use encoding_rs::{Decoder, Encoding};
+use std::io::{self, Read};
+use thiserror::Error;
 
 /// Check if data contains only ASCII characters (7-bit)
 fn is_ascii(data: &[u8]) -> bool {
     data.iter().all(|&b| b < 128)
 }
 
+/// Valid UTF-8 that decodes to C1 controls (U+0080..U+009F) is legacy
+/// 8-bit text that only happens to be well-formed, e.g. cp1252 `Â’`
+fn is_coincidental_utf8(data: &[u8]) -> bool {
+    std::str::from_utf8(data)
+        .is_ok_and(|text| text.chars().any(|c| ('\u{80}'..='\u{9f}').contains(&c)))
+}
+
 /// UTF-8 BOM bytes
 const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
 
@@ -19,8 +28,33 @@ const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
 /// Allow guessing UTF-8 encoding
 const ALLOW_UTF8: bool = true;
 
-/// Detect the character encoding of the given data.
-/// Returns a normalized encoding name.
+/// UTF-16 without BOM, from the NUL bytes of mostly Latin text: the high
+/// byte of at least 70% of the characters is 0, the low byte of at most 5%
+fn detect_utf16_without_bom(data: &[u8]) -> Option<&'static str> {
+    let pairs = data.len() / 2;
+    if pairs < 4 {
+        return None;
+    }
+    let (mut even, mut odd) = (0, 0);
+    for pair in data.chunks_exact(2) {
+        even += usize::from(pair[0] == 0);
+        odd += usize::from(pair[1] == 0);
+    }
+    let mostly = |count: usize| count * 100 >= pairs * 70;
+    let rarely = |count: usize| count * 100 <= pairs * 5;
+    if mostly(odd) && rarely(even) {
+        Some("UTF-16LE")
+    } else if mostly(even) && rarely(odd) {
+        Some("UTF-16BE")
+    } else {
+        None
+    }
+}
+
+/// Detect the character encoding of the given data, whatever its size:
+/// BOM, then UTF-16 without BOM, then pure ASCII, then statistical
+/// detection by chardetng (which prefers UTF-8 for valid UTF-8, unless it
+/// is only valid by coincidence). Returns a normalized encoding name.
 pub fn detect_charset(data: &[u8]) -> String {
     // Check for BOM markers first
     match data {
@@ -29,10 +63,9 @@ pub fn detect_charset(data: &[u8]) -> String {
         d if d.len() >= 2 && d.starts_with(UTF16_BE_BOM) => return "UTF-16BE".to_string(),
         _ => {}
     }
-
-    // For small files, use quick encoding guess
-    if data.len() <= CSVA_GUESS_SIZE {
-        return guess_encoding_quick(data);
+    // NUL bytes would pass for ASCII
+    if let Some(utf16) = detect_utf16_without_bom(data) {
+        return utf16.to_string();
     }
 
     // Check if pure ASCII first (matches Pascal behavior: CodePage 0 = ASCII = 'Ansi')
@@ -40,34 +73,13 @@ pub fn detect_charset(data: &[u8]) -> String {
         return "ansi".to_string();
     }
 
-    // Use chardetng for larger files
     let mut detector = EncodingDetector::new();
     detector.feed(data, true);
-    let encoding = detector.guess(None, ALLOW_UTF8);
+    let encoding = detector.guess(None, ALLOW_UTF8 && !is_coincidental_utf8(data));
 
     normalize_encoding(encoding.name())
 }
 
-/// Quick encoding detection for small samples
-fn guess_encoding_quick(data: &[u8]) -> String {
-    // Check if pure ASCII (matches Pascal behavior: CodePage 0 = ASCII = 'Ansi')
-    if is_ascii(data) {
-        return "ansi".to_string();
-    }
-
-    // Check if valid UTF-8
-    match std::str::from_utf8(data) {
-        Ok(_) => "utf8".to_string(),
-        Err(_) => {
-            // Use chardetng for non-UTF-8
-            let mut detector = EncodingDetector::new();
-            detector.feed(data, true);
-            let encoding = detector.guess(None, ALLOW_UTF8);
-            normalize_encoding(encoding.name())
-        }
-    }
-}
-
 /// Normalize encoding name to match Pascal implementation
 fn normalize_encoding(name: &str) -> String {
     let name_lower = name.to_lowercase();
@@ -79,13 +91,118 @@ fn normalize_encoding(name: &str) -> String {
         "iso-8859-15" | "iso8859-15" | "latin9" => "iso885915".to_string(),
         "windows-1252" | "cp1252" => "cp1252".to_string(),
         "windows-1251" | "cp1251" => "cp1251".to_string(),
+        "gb18030" => "gb18030".to_string(),
+        "gbk" | "gb2312" => "gbk".to_string(),
+        "shift_jis" | "shift-jis" | "sjis" => "shiftjis".to_string(),
+        "euc-jp" => "eucjp".to_string(),
+        "big5" => "big5".to_string(),
         "ascii" => "ansi".to_string(), // ASCII detected as 'ansi' (Pascal CodePage 0)
         _ => name_lower.replace("-", "").replace("_", ""),
     }
 }
 
-/// Convert data from detected charset to UTF-8
-pub fn convert_to_utf8(data: &[u8], charset: &str) -> Result<String, String> {
+/// Human-readable label of a normalized charset name (e.g. `Windows-1252`
+/// for `cp1252`), the name itself when unknown
+pub fn charset_label(charset: &str) -> String {
+    let label = match charset.to_lowercase().as_str() {
+        "ansi" | "ascii" => "US-ASCII",
+        "utf8" | "utf-8" => "UTF-8",
+        "utf-8bom" => "UTF-8 (with BOM)",
+        "utf-16le" | "utf16le" => "UTF-16LE",
+        "utf-16be" | "utf16be" => "UTF-16BE",
+        "iso88591" => "ISO-8859-1 (Latin-1)",
+        "iso885915" => "ISO-8859-15 (Latin-9)",
+        "windows1250" => "Windows-1250",
+        "cp1251" => "Windows-1251",
+        "cp1252" => "Windows-1252",
+        "windows1253" => "Windows-1253",
+        "windows1254" => "Windows-1254",
+        "windows1255" => "Windows-1255",
+        "windows1256" => "Windows-1256",
+        "windows1257" => "Windows-1257",
+        "windows1258" => "Windows-1258",
+        "iso88592" => "ISO-8859-2 (Latin-2)",
+        "gb18030" => "GB18030",
+        "gbk" => "GBK",
+        "shiftjis" => "Shift_JIS",
+        "eucjp" => "EUC-JP",
+        "big5" => "Big5",
+        _ => return charset.to_string(),
+    };
+    label.to_string()
+}
+
+/// Check if a charset is UTF-16, whose raw bytes are mostly NUL for Latin text
+pub fn is_utf16(charset: &str) -> bool {
+    matches!(
+        charset.to_lowercase().as_str(),
+        "utf-16le" | "utf16le" | "utf-16be" | "utf16be"
+    )
+}
+
+/// Offset just past the last complete line feed of `data` in the charset:
+/// the `0A 00`/`00 0A` code unit for UTF-16, the `\n` byte otherwise
+pub fn last_line_end(data: &[u8], charset: &str) -> Option<usize> {
+    let newline: &[u8] = match charset.to_lowercase().as_str() {
+        "utf-16le" | "utf16le" => &[0x0A, 0x00],
+        "utf-16be" | "utf16be" => &[0x00, 0x0A],
+        _ => return memchr::memrchr(b'\n', data).map(|pos| pos + 1),
+    };
+    data.chunks_exact(2)
+        .rposition(|unit| unit == newline)
+        .map(|unit| unit * 2 + 2)
+}
+
+/// Remove NUL characters left in the text by a bad export.
+/// Returns the text without them and the lines (1-based) they were found on.
+pub fn strip_nul_chars(text: String) -> (String, Vec<usize>) {
+    if !text.contains('\0') {
+        return (text, Vec::new());
+    }
+    let lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains('\0'))
+        .map(|(i, _)| i + 1)
+        .collect();
+    (text.replace('\0', ""), lines)
+}
+
+/// Why data could not be converted to UTF-8
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    /// Not valid UTF-8 for a UTF-8 or ASCII charset
+    #[error("{0}")]
+    InvalidUtf8(String),
+    /// More characters could not be decoded than the allowed share
+    #[error("{0}")]
+    TooManyErrors(String),
+}
+
+/// Convert data from detected charset to UTF-8.
+/// Bytes invalid for a non-UTF-8 charset become replacement chars.
+pub fn convert_to_utf8(data: &[u8], charset: &str) -> Result<String, ConvertError> {
+    convert_to_utf8_with(data, charset, false)
+}
+
+/// Convert data from detected charset to UTF-8, failing on bytes invalid
+/// for the charset when `strict` is set
+pub fn convert_to_utf8_with(
+    data: &[u8],
+    charset: &str,
+    strict: bool,
+) -> Result<String, ConvertError> {
+    convert_to_utf8_max_errors(data, charset, strict.then_some(0.0))
+}
+
+/// Convert data from detected charset to UTF-8, failing when the share of
+/// characters that could not be decoded exceeds `max_error_ratio`
+/// (between 0 and 1). Any share is tolerated when unset.
+pub fn convert_to_utf8_max_errors(
+    data: &[u8],
+    charset: &str,
+    max_error_ratio: Option<f64>,
+) -> Result<String, ConvertError> {
     let charset_lower = charset.to_lowercase();
 
     // Strip BOM if present
@@ -101,11 +218,43 @@ pub fn convert_to_utf8(data: &[u8], charset: &str) -> Result<String, String> {
         charset_lower.as_str(),
         "utf8" | "utf-8" | "utf-8bom" | "ansi" | "ascii"
     ) {
-        return String::from_utf8(data.to_vec()).map_err(|e| format!("Invalid UTF-8: {}", e));
+        let text = String::from_utf8(data.to_vec())
+            .map_err(|e| ConvertError::InvalidUtf8(format!("Invalid UTF-8: {}", e)))?;
+        return Ok(strip_bom(text));
+    }
+
+    let encoding = encoding_for_charset(charset);
+    let (decoded, _, had_errors) = encoding.decode(data);
+    if let (true, Some(max_ratio)) = (had_errors, max_error_ratio) {
+        let total = decoded.chars().count();
+        let replaced = decoded.chars().filter(|&c| c == '\u{FFFD}').count();
+        if replaced as f64 > max_ratio * total as f64 {
+            return Err(ConvertError::TooManyErrors(format!(
+                "Invalid {} content: {} of {} characters could not be decoded",
+                encoding.name(),
+                replaced,
+                total
+            )));
+        }
     }
+    // Malformed input is returned with replacement chars
+    Ok(strip_bom(decoded.into_owned()))
+}
+
+/// Remove a leading U+FEFF left by a BOM the charset did not account for
+/// (e.g. a UTF-8 BOM in a file read as "utf8"), which would otherwise stick
+/// to the first header
+fn strip_bom(text: String) -> String {
+    match text.strip_prefix('\u{FEFF}') {
+        Some(rest) => rest.to_string(),
+        None => text,
+    }
+}
 
-    // Get encoding from charset name
-    let encoding = match charset_lower.as_str() {
+/// Get the encoding for a charset name, UTF-8 when unknown
+pub fn encoding_for_charset(charset: &str) -> &'static Encoding {
+    match charset.to_lowercase().as_str() {
+        "utf8" | "utf-8" | "utf-8bom" | "ansi" | "ascii" => encoding_rs::UTF_8,
         "utf16le" | "utf-16le" => encoding_rs::UTF_16LE,
         "utf16be" | "utf-16be" => encoding_rs::UTF_16BE,
         "iso88591" | "iso-8859-1" | "latin1" => encoding_rs::WINDOWS_1252, // Superset
@@ -113,16 +262,89 @@ pub fn convert_to_utf8(data: &[u8], charset: &str) -> Result<String, String> {
         "cp1252" | "windows-1252" | "windows1252" => encoding_rs::WINDOWS_1252,
         "cp1251" | "windows-1251" | "windows1251" => encoding_rs::WINDOWS_1251,
         "cp1250" | "windows-1250" | "windows1250" => encoding_rs::WINDOWS_1250,
+        "gb18030" => encoding_rs::GB18030,
+        "gbk" | "gb2312" => encoding_rs::GBK,
+        "shiftjis" | "shift_jis" | "shift-jis" | "sjis" => encoding_rs::SHIFT_JIS,
+        "eucjp" | "euc-jp" => encoding_rs::EUC_JP,
+        "big5" => encoding_rs::BIG5,
         // Try to get encoding by name, or fallback to UTF-8
         _ => Encoding::for_label(charset.as_bytes()).unwrap_or(encoding_rs::UTF_8),
-    };
+    }
+}
 
-    let (decoded, _, had_errors) = encoding.decode(data);
-    if had_errors {
-        // For now, return the decoded content with replacement chars
-        Ok(decoded.into_owned())
-    } else {
-        Ok(decoded.into_owned())
+/// Lines of a reader decoded to UTF-8 chunk by chunk, so a whole file can
+/// be scanned without loading it in memory. Line endings are stripped.
+pub struct DecodedLines<R: Read> {
+    reader: R,
+    decoder: Decoder,
+    buffer: String,
+    eof: bool,
+    first_line: bool,
+}
+
+impl<R: Read> DecodedLines<R> {
+    pub fn new(reader: R, charset: &str) -> Self {
+        DecodedLines {
+            reader,
+            decoder: encoding_for_charset(charset).new_decoder_with_bom_removal(),
+            buffer: String::new(),
+            eof: false,
+            first_line: true,
+        }
+    }
+
+    /// Decode the next chunk of the reader into the buffer
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = vec![0u8; BUFF_SIZE];
+        let bytes_read = self.reader.read(&mut chunk)?;
+        self.eof = bytes_read == 0;
+
+        if let Some(len) = self.decoder.max_utf8_buffer_length(bytes_read) {
+            self.buffer.reserve(len);
+        }
+        // Malformed input becomes replacement chars, as in convert_to_utf8
+        let _ = self
+            .decoder
+            .decode_to_string(&chunk[..bytes_read], &mut self.buffer, self.eof);
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for DecodedLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.next_line();
+        if std::mem::take(&mut self.first_line) {
+            return line.map(|l| l.map(strip_bom));
+        }
+        line
+    }
+}
+
+impl<R: Read> DecodedLines<R> {
+    fn next_line(&mut self) -> Option<io::Result<String>> {
+        loop {
+            if let Some(pos) = self.buffer.find('\n') {
+                let mut line: String = self.buffer.drain(..=pos).collect();
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+                return Some(Ok(line));
+            }
+
+            if self.eof {
+                if self.buffer.is_empty() {
+                    return None;
+                }
+                return Some(Ok(std::mem::take(&mut self.buffer)));
+            }
+
+            if let Err(e) = self.fill() {
+                return Some(Err(e));
+            }
+        }
     }
 }
 
@@ -130,6 +352,17 @@ pub fn convert_to_utf8(data: &[u8], charset: &str) -> Result<String, String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_strip_nul_chars() {
+        let (text, lines) = strip_nul_chars("a,b\nc\0d,e\nf\0,\0g\n".to_string());
+        assert_eq!(text, "a,b\ncd,e\nf,g\n");
+        assert_eq!(lines, vec![2, 3]);
+
+        let (text, lines) = strip_nul_chars("a,b\n".to_string());
+        assert_eq!(text, "a,b\n");
+        assert!(lines.is_empty());
+    }
+
     #[test]
     fn test_detect_utf8_bom() {
         let data = [0xEF, 0xBB, 0xBF, b'h', b'e', b'l', b'l', b'o'];
@@ -142,6 +375,42 @@ mod tests {
         assert_eq!(detect_charset(&data), "UTF-16LE");
     }
 
+    #[test]
+    fn test_last_line_end() {
+        assert_eq!(last_line_end(b"a,b\nc,d\ne,", "utf8"), Some(8));
+        assert_eq!(last_line_end(b"a,b", "utf8"), None);
+
+        // "a\nb\n" cut after the second line feed's first byte
+        let le = [0x61, 0x00, 0x0A, 0x00, 0x62, 0x00, 0x0A];
+        assert_eq!(last_line_end(&le, "UTF-16LE"), Some(4));
+        let be = [0x00, 0x61, 0x00, 0x0A, 0x00, 0x62, 0x00];
+        assert_eq!(last_line_end(&be, "UTF-16BE"), Some(4));
+        // U+0A0A is not a line feed
+        assert_eq!(last_line_end(&[0x0A, 0x0A], "UTF-16LE"), None);
+    }
+
+    #[test]
+    fn test_detect_utf16_without_bom() {
+        let le: Vec<u8> = "email,name\nRenée,Zoë\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(detect_charset(&le), "UTF-16LE");
+        assert_eq!(
+            convert_to_utf8(&le, "UTF-16LE").unwrap(),
+            "email,name\nRenée,Zoë\n"
+        );
+
+        let be: Vec<u8> = "email,name\n"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        assert_eq!(detect_charset(&be), "UTF-16BE");
+
+        // A few NUL bytes don't make UTF-16
+        assert_eq!(detect_charset(b"email,name\0\njohn,x\n"), "ansi");
+    }
+
     #[test]
     fn test_detect_ascii() {
         let data = b"Hello, World!";
@@ -177,6 +446,22 @@ mod tests {
         assert_eq!(detect_charset(data), "utf8");
     }
 
+    #[test]
+    fn test_detect_small_latin1() {
+        // ~4KB of cp1252 rows whose `Â“`/`Â ”` happen to be valid UTF-8
+        // (decoding to C1 controls), which the quick UTF-8 check took for utf8
+        let data = b"email,note\njose@example.com,\xc2\x93Gr\xc2\xa0\xc2\x94\n".repeat(80);
+        assert!(std::str::from_utf8(&data).is_ok());
+        assert!(data.len() < 5120);
+        assert_eq!(detect_charset(&data), "cp1252");
+
+        // Real UTF-8 text is still utf8
+        assert_eq!(
+            detect_charset("jose@example.com,“Café”\n".as_bytes()),
+            "utf8"
+        );
+    }
+
     #[test]
     fn test_is_ascii_helper() {
         // Test the is_ascii helper function
@@ -194,6 +479,117 @@ mod tests {
         assert_eq!(normalize_encoding("ascii"), "ansi");
     }
 
+    #[test]
+    fn test_is_utf16() {
+        assert!(is_utf16("UTF-16LE"));
+        assert!(is_utf16("utf16be"));
+        assert!(!is_utf16("utf8"));
+    }
+
+    #[test]
+    fn test_decoded_lines() {
+        let lines: Vec<String> = DecodedLines::new(&b"a,b\r\n1,2\n3,4"[..], "ansi")
+            .map(|l| l.unwrap())
+            .collect();
+        assert_eq!(lines, vec!["a,b", "1,2", "3,4"]);
+
+        // UTF-16 newlines span two bytes, and chunks may split characters
+        let mut bytes = vec![0xFF, 0xFE];
+        let text = format!("{}\néa\n", "x".repeat(BUFF_SIZE));
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        let lines: Vec<String> = DecodedLines::new(&bytes[..], "UTF-16LE")
+            .map(|l| l.unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), BUFF_SIZE);
+        assert_eq!(lines[1], "éa");
+    }
+
+    #[test]
+    fn test_convert_strips_bom() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("email,name\n".encode_utf16().flat_map(u16::to_le_bytes));
+        let text = convert_to_utf8(&bytes, &detect_charset(&bytes)).unwrap();
+        assert!(text.starts_with("email"));
+
+        // A BOM inside the UTF-16 payload as well as a UTF-8 BOM read as utf8
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("\u{FEFF}email".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(convert_to_utf8(&bytes, "UTF-16LE").unwrap(), "email");
+        assert_eq!(
+            convert_to_utf8(b"\xEF\xBB\xBFemail", "utf8").unwrap(),
+            "email"
+        );
+    }
+
+    #[test]
+    fn test_normalize_encoding_cjk() {
+        assert_eq!(normalize_encoding("gb18030"), "gb18030");
+        assert_eq!(normalize_encoding("GBK"), "gbk");
+        assert_eq!(normalize_encoding("Shift_JIS"), "shiftjis");
+        assert_eq!(normalize_encoding("EUC-JP"), "eucjp");
+        assert_eq!(normalize_encoding("Big5"), "big5");
+    }
+
+    #[test]
+    fn test_convert_cjk_round_trip() {
+        let text = "email,名前,都市\ntaro@example.com,山田太郎,東京\n";
+        for (encoding, charset) in [
+            (encoding_rs::GB18030, "gb18030"),
+            (encoding_rs::SHIFT_JIS, "shiftjis"),
+            (encoding_rs::EUC_JP, "eucjp"),
+            (encoding_rs::BIG5, "big5"),
+        ] {
+            let (bytes, _, had_errors) = encoding.encode(text);
+            assert!(!had_errors);
+            assert_eq!(convert_to_utf8(&bytes, charset).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_charset_label() {
+        assert_eq!(charset_label("cp1252"), "Windows-1252");
+        assert_eq!(charset_label("ansi"), "US-ASCII");
+        assert_eq!(charset_label("iso88591"), "ISO-8859-1 (Latin-1)");
+        assert_eq!(charset_label("UTF-8BOM"), "UTF-8 (with BOM)");
+        assert_eq!(charset_label("koi8r"), "koi8r");
+
+        // Through detection, whose names fall back to e.g. `windows1250`
+        let polish = b"imie,miasto\nZofia,\xa3\xf3d\x9f\nJerzy,Gda\xf1sk\nKasia,Bia\xb3ystok\nPiotr,Krak\xf3w\n\
+            Ma\xb3gorzata,Szczecin\nGrzegorz,\x8cwi\xeatoch\xb3owice\nJoanna,W\xb9chock\nAnna,Cz\xeastochowa\n";
+        let charset = detect_charset(polish);
+        assert_eq!(charset, "windows1250");
+        assert_eq!(charset_label(&charset), "Windows-1250");
+    }
+
+    #[test]
+    fn test_convert_max_error_ratio() {
+        // One invalid Shift_JIS sequence in a large file
+        let mut data = b"email,name\n".repeat(100);
+        data.extend_from_slice(b"Jo\x81\x20\n");
+        assert!(convert_to_utf8_max_errors(&data, "shiftjis", Some(0.01)).is_ok());
+        assert!(convert_to_utf8_max_errors(&data, "shiftjis", Some(0.0)).is_err());
+
+        // Mostly invalid sequences
+        let corrupt = b"a\x81\x20".repeat(100);
+        let err = convert_to_utf8_max_errors(&corrupt, "shiftjis", Some(0.01)).unwrap_err();
+        assert!(matches!(err, ConvertError::TooManyErrors(_)));
+        assert!(err.to_string().contains("100 of 300"));
+        assert!(convert_to_utf8_max_errors(&corrupt, "shiftjis", None).is_ok());
+    }
+
+    #[test]
+    fn test_convert_strict() {
+        // 0x81 followed by a byte below 0x40 is not a valid Shift_JIS sequence
+        let data = b"email\x81\x20,name";
+        let lossy = convert_to_utf8(data, "shiftjis").unwrap();
+        assert!(lossy.contains('\u{FFFD}'));
+
+        let err = convert_to_utf8_with(data, "shiftjis", true).unwrap_err();
+        assert!(err.to_string().contains("Shift_JIS"));
+        assert!(convert_to_utf8_with(b"email,name", "shiftjis", true).is_ok());
+    }
+
     #[test]
     fn test_convert_utf8() {
         let data = b"Hello";
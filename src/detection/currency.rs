@@ -0,0 +1,147 @@
+/// Currency symbols recognized before or after an amount
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥', '₹', '₽', '₩'];
+
+/// Parse a monetary value like "$1,234.56", "€9,99" or "1.234,56 €".
+/// Returns the currency symbol and the amount.
+pub fn parse_currency(value: &str) -> Option<(char, f64)> {
+    let value = value.trim();
+
+    let (symbol, amount) = match (value.chars().next(), value.chars().last()) {
+        (Some(c), _) if CURRENCY_SYMBOLS.contains(&c) => (c, &value[c.len_utf8()..]),
+        (_, Some(c)) if CURRENCY_SYMBOLS.contains(&c) => (c, &value[..value.len() - c.len_utf8()]),
+        _ => return None,
+    };
+
+    parse_grouped_number(amount.trim()).map(|amount| (symbol, amount))
+}
+
+/// Detect whether a column holds amounts in a single currency.
+/// Returns the currency symbol when every non-empty value is an amount using it.
+pub fn detect_currency_column(values: &[&str]) -> Option<char> {
+    let mut column_symbol: Option<char> = None;
+
+    for value in values {
+        if value.trim().is_empty() {
+            continue;
+        }
+
+        let (symbol, _) = parse_currency(value)?;
+        match column_symbol {
+            None => column_symbol = Some(symbol),
+            Some(s) if s != symbol => return None,
+            Some(_) => {}
+        }
+    }
+
+    column_symbol
+}
+
+/// Parse a number that may use ',' or '.' as grouping or decimal separator
+fn parse_grouped_number(value: &str) -> Option<f64> {
+    let (sign, digits) = match value.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", value),
+    };
+
+    if digits.is_empty()
+        || !digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+    {
+        return None;
+    }
+
+    let last_comma = digits.rfind(',');
+    let last_dot = digits.rfind('.');
+
+    // Work out which separator (if any) is the decimal one
+    let decimal_sep = match (last_comma, last_dot) {
+        (Some(c), Some(d)) => Some(if c > d { ',' } else { '.' }),
+        (Some(_), None) => single_separator_role(digits, ','),
+        (None, Some(_)) => single_separator_role(digits, '.'),
+        (None, None) => None,
+    };
+
+    let (int_part, frac_part) = match decimal_sep {
+        Some(sep) => {
+            let pos = digits.rfind(sep)?;
+            (&digits[..pos], &digits[pos + 1..])
+        }
+        None => (digits, ""),
+    };
+
+    if frac_part.contains([',', '.']) {
+        return None;
+    }
+
+    // Remaining separators in the integer part must form groups of three
+    let groups: Vec<&str> = int_part.split([',', '.']).collect();
+    if groups.len() > 1
+        && (groups[0].is_empty() || groups[0].len() > 3 || groups[1..].iter().any(|g| g.len() != 3))
+    {
+        return None;
+    }
+
+    format!("{}{}.{}", sign, groups.concat(), frac_part)
+        .trim_end_matches('.')
+        .parse::<f64>()
+        .ok()
+}
+
+/// Decide whether a lone separator kind is a decimal separator.
+/// A single occurrence followed by exactly three digits is taken as grouping.
+fn single_separator_role(digits: &str, sep: char) -> Option<char> {
+    let occurrences = digits.matches(sep).count();
+    let after = digits.len() - digits.rfind(sep)? - 1;
+
+    if occurrences > 1 || after == 3 {
+        None
+    } else {
+        Some(sep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dollar() {
+        assert_eq!(parse_currency("$1,234.56"), Some(('$', 1234.56)));
+        assert_eq!(parse_currency("$10"), Some(('$', 10.0)));
+        assert_eq!(parse_currency("$1,234,567"), Some(('$', 1234567.0)));
+    }
+
+    #[test]
+    fn test_parse_euro() {
+        assert_eq!(parse_currency("€9,99"), Some(('€', 9.99)));
+        assert_eq!(parse_currency("1.234,56 €"), Some(('€', 1234.56)));
+        assert_eq!(parse_currency("-5,50€"), Some(('€', -5.5)));
+    }
+
+    #[test]
+    fn test_parse_pound() {
+        assert_eq!(parse_currency("£10"), Some(('£', 10.0)));
+        assert_eq!(parse_currency("£0.99"), Some(('£', 0.99)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_currency("10"), None);
+        assert_eq!(parse_currency("$"), None);
+        assert_eq!(parse_currency("$abc"), None);
+        assert_eq!(parse_currency("$1,23,4"), None);
+    }
+
+    #[test]
+    fn test_detect_currency_column() {
+        assert_eq!(detect_currency_column(&["$1,234.56", "", "$10"]), Some('$'));
+        assert_eq!(detect_currency_column(&["1.234,56 €", "€9,99"]), Some('€'));
+        assert_eq!(detect_currency_column(&["£10", "£2.50"]), Some('£'));
+
+        // Mixed currencies or plain numbers are not matched
+        assert_eq!(detect_currency_column(&["$10", "€10"]), None);
+        assert_eq!(detect_currency_column(&["$10", "10"]), None);
+        assert_eq!(detect_currency_column(&[""]), None);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::detection::datetime::{could_be_datetime, guess_datetime_format, DateTimePatterns};
+use crate::detection::datetime::{
+    could_be_datetime, guess_datetime_format, has_month_name, DateTimePatterns,
+};
+use crate::detection::phone::is_phone_like;
 use crate::types::DataType;
 
 /// Tracking state for boolean detection
@@ -39,49 +42,104 @@ pub fn detect_value_type(value: &str, bool_state: &mut BooleanState) -> DataType
     }
 }
 
-/// Detect the data type for an entire column
+/// Incremental data type detection for one column, fed a value at a time.
 /// Uses the "downgrading" strategy from Pascal implementation
-pub fn detect_data_type(
-    values: &[&str],
-    meta_type: Option<DataType>,
-) -> (DataType, Option<DateTimePatterns>) {
-    if values.is_empty() {
-        return (meta_type.unwrap_or(DataType::String), None);
+#[derive(Debug, Clone, Default)]
+pub struct ColumnTypeState {
+    current_type: Option<DataType>,
+    bool_state: BooleanState,
+    datetime_patterns: Option<DateTimePatterns>,
+    /// Whether any value, blank included, was seen
+    seen: bool,
+}
+
+impl ColumnTypeState {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    let mut current_type: Option<DataType> = None;
-    let mut bool_state = BooleanState::default();
-    let mut datetime_patterns: Option<DateTimePatterns> = None;
+    /// Refine the column type with the next value
+    pub fn update(&mut self, value: &str) {
+        self.seen = true;
+        if self.is_string() {
+            return;
+        }
 
-    for value in values {
         let value = value.trim();
         if value.is_empty() {
-            continue;
+            return;
         }
 
-        let value_type = detect_value_with_patterns(value, &mut bool_state, &mut datetime_patterns);
+        // Any String value downgrades the column to String, so conclude
+        // right away for obvious text without attempting datetime patterns
+        if is_clearly_string(value) {
+            self.current_type = Some(DataType::String);
+            return;
+        }
 
-        current_type = match current_type {
-            None => Some(value_type),
-            Some(ct) if ct == value_type => Some(ct),
-            Some(ct) => {
-                let new_type = downgrade_types(ct, value_type, &bool_state);
-                if new_type == DataType::String {
-                    return (DataType::String, None);
-                }
-                Some(new_type)
-            }
+        let value_type =
+            detect_value_with_patterns(value, &mut self.bool_state, &mut self.datetime_patterns);
+
+        self.current_type = Some(match self.current_type {
+            None => value_type,
+            Some(ct) if ct == value_type => ct,
+            Some(ct) => downgrade_types(ct, value_type, &self.bool_state),
+        });
+    }
+
+    /// Whether the column settled on String, which no further value can change
+    pub fn is_string(&self) -> bool {
+        self.current_type == Some(DataType::String)
+    }
+
+    /// Final type, `meta_type` applies when no value was seen
+    pub fn finish(self, meta_type: Option<DataType>) -> (DataType, Option<DateTimePatterns>) {
+        if !self.seen {
+            return (meta_type.unwrap_or(DataType::String), None);
+        }
+
+        let final_type = self.current_type.unwrap_or(DataType::String);
+        let patterns = if final_type == DataType::DateTime {
+            self.datetime_patterns
+        } else {
+            None
         };
+
+        (final_type, patterns)
+    }
+}
+
+/// Detect the data type for an entire column
+pub fn detect_data_type(
+    values: &[&str],
+    meta_type: Option<DataType>,
+) -> (DataType, Option<DateTimePatterns>) {
+    let mut state = ColumnTypeState::new();
+    for value in values {
+        state.update(value);
+        if state.is_string() {
+            break;
+        }
     }
 
-    let final_type = current_type.unwrap_or(DataType::String);
-    let patterns = if final_type == DataType::DateTime {
-        datetime_patterns
-    } else {
-        None
-    };
+    state.finish(meta_type)
+}
 
-    (final_type, patterns)
+/// Detect the data type of every column from a stream of rows, without
+/// holding more than one row at a time
+pub fn detect_column_types<I, R>(rows: I, columns: usize) -> Vec<ColumnTypeState>
+where
+    I: IntoIterator<Item = R>,
+    R: AsRef<[String]>,
+{
+    let mut states = vec![ColumnTypeState::new(); columns];
+    for row in rows {
+        for (state, value) in states.iter_mut().zip(row.as_ref()) {
+            state.update(value);
+        }
+    }
+
+    states
 }
 
 /// Helper to detect value type while managing datetime patterns
@@ -106,6 +164,12 @@ fn detect_value_with_patterns(
         if guess_datetime_format(value, patterns) {
             return DataType::DateTime;
         }
+        // A date ruling out every pattern kept for the earlier values, e.g.
+        // "04/13/2020" after "13/04/2020", leaves no format to report
+        return match detect_value_type(value, bool_state) {
+            DataType::DateTime => DataType::String,
+            vt => vt,
+        };
     } else {
         let mut patterns = DateTimePatterns::new();
         if guess_datetime_format(value, &mut patterns) {
@@ -128,6 +192,8 @@ fn downgrade_types(type1: DataType, type2: DataType, bool_state: &BooleanState)
         (Integer, Float) | (Float, Integer) => Float,
         // Boolean + Integer (when no string bools) -> Integer
         (Boolean, Integer) | (Integer, Boolean) if !bool_state.had_string_bool => Integer,
+        // Boolean + Float (when no string bools) -> Float, in either order
+        (Boolean, Float) | (Float, Boolean) if !bool_state.had_string_bool => Float,
         // Boolean with string bools + anything else -> String
         (Boolean, _) | (_, Boolean) if bool_state.had_string_bool => String,
         // Otherwise, downgrade until compatible or String
@@ -168,12 +234,45 @@ fn is_string_value(value: &str) -> bool {
         return false;
     }
 
+    // International phone numbers like "+4930123456" parse as integers but
+    // would lose their '+' and any leading zero
+    if value.starts_with('+') && is_phone_like(value) {
+        return true;
+    }
+
     // Check if contains non-numeric characters
     value
         .chars()
         .any(|c| !matches!(c, '0'..='9' | '.' | ',' | '-' | '+'))
 }
 
+/// Check if a value is text that cannot be any other type.
+/// Letters other than those found in datetimes (RFC3339 'T'/'Z', am/pm,
+/// month names) rule out every non-String type except the true/false booleans.
+fn is_clearly_string(value: &str) -> bool {
+    is_string_value(value)
+        && !has_month_name(value)
+        && value.chars().any(|c| {
+            c.is_alphabetic() && !matches!(c.to_ascii_lowercase(), 't' | 'z' | 'a' | 'p' | 'm')
+        })
+}
+
+/// Map full-width digits and numeric signs (U+FF10..U+FF19, `＋－．，`) to
+/// their ASCII forms so East Asian exports parse as numbers
+pub fn normalize_fullwidth_digits(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '\u{FF10}'..='\u{FF19}' => char::from(b'0' + (c as u32 - 0xFF10) as u8),
+            '\u{FF0B}' => '+',
+            '\u{FF0C}' => ',',
+            '\u{FF0D}' => '-',
+            '\u{FF0E}' => '.',
+            _ => c,
+        })
+        .collect()
+}
+
 /// Try to parse as boolean, returns Some(is_string_form) if valid
 fn try_parse_boolean(value: &str) -> Option<bool> {
     let value_lower = value.to_lowercase();
@@ -212,6 +311,16 @@ mod tests {
         let mut bs = BooleanState::default();
         assert_eq!(detect_value_type("123", &mut bs), DataType::Integer);
         assert_eq!(detect_value_type("-456", &mut bs), DataType::Integer);
+        assert_eq!(detect_value_type("+456", &mut bs), DataType::Integer);
+    }
+
+    #[test]
+    fn test_international_phone_stays_string() {
+        let mut bs = BooleanState::default();
+        assert_eq!(detect_value_type("+4930123456", &mut bs), DataType::String);
+
+        let values = vec!["+4930123456", "+4989765432", "+33142685300"];
+        assert_eq!(detect_data_type(&values, None).0, DataType::String);
     }
 
     #[test]
@@ -232,6 +341,59 @@ mod tests {
         assert!(!bs2.had_string_bool);
     }
 
+    #[test]
+    fn test_is_clearly_string() {
+        assert!(is_clearly_string("hello"));
+        assert!(is_clearly_string("2020-01-15 hello"));
+        assert!(!is_clearly_string("true"));
+        assert!(!is_clearly_string("2020-01-15T10:30:00Z"));
+        assert!(!is_clearly_string("10:30 pm"));
+        assert!(!is_clearly_string("12.5"));
+    }
+
+    #[test]
+    fn test_normalize_fullwidth_digits() {
+        assert_eq!(normalize_fullwidth_digits("１２３"), "123");
+        assert_eq!(normalize_fullwidth_digits("－４５．６"), "-45.6");
+        assert_eq!(normalize_fullwidth_digits("abc 12"), "abc 12");
+
+        let (dt, _) = detect_data_type(&["１２３", "４５６"], None);
+        assert_eq!(dt, DataType::String);
+        let normalized: Vec<String> = ["１２３", "４５６"]
+            .iter()
+            .map(|v| normalize_fullwidth_digits(v))
+            .collect();
+        let values: Vec<&str> = normalized.iter().map(String::as_str).collect();
+        let (dt, _) = detect_data_type(&values, None);
+        assert_eq!(dt, DataType::Integer);
+    }
+
+    #[test]
+    fn test_detect_column_types() {
+        let rows = vec![
+            vec!["1".to_string(), "a".to_string()],
+            vec!["2".to_string(), "b".to_string()],
+            vec!["2.5".to_string()],
+        ];
+        let types: Vec<DataType> = detect_column_types(rows, 3)
+            .into_iter()
+            .map(|state| state.finish(None).0)
+            .collect();
+        assert_eq!(
+            types,
+            vec![DataType::Float, DataType::String, DataType::String]
+        );
+    }
+
+    #[test]
+    fn test_column_type_early_string() {
+        // The first value settles the column, later dates are never attempted
+        let values = vec!["Customer since spring", "2020-01-15", "2020-02-15"];
+        let (dt, patterns) = detect_data_type(&values, None);
+        assert_eq!(dt, DataType::String);
+        assert!(patterns.is_none());
+    }
+
     #[test]
     fn test_column_type_detection() {
         let values = vec!["1", "2", "3", "4"];
@@ -253,6 +415,23 @@ mod tests {
         assert_eq!(dt, DataType::String);
     }
 
+    #[test]
+    fn test_date_orders_eliminated_by_later_values() {
+        // Ambiguous dates, then one ruling out each order: no pattern is left
+        let values = vec!["03/04/2020", "05/06/2020", "", "04/13/2020", "13/04/2020"];
+        let (dt, patterns) = detect_data_type(&values, None);
+        assert_eq!(dt, DataType::String);
+        assert!(patterns.is_none());
+    }
+
+    #[test]
+    fn test_conflicting_date_orders() {
+        let values = vec!["13/04/2020", "04/13/2020"];
+        let (dt, patterns) = detect_data_type(&values, None);
+        assert_eq!(dt, DataType::String);
+        assert!(patterns.is_none());
+    }
+
     #[test]
     fn test_boolean_integer_downgrade() {
         // Boolean (0/1) + larger integer -> Integer
@@ -268,4 +447,21 @@ mod tests {
         let (dt, _) = detect_data_type(&values, None);
         assert_eq!(dt, DataType::String);
     }
+
+    #[test]
+    fn test_boolean_float_downgrade() {
+        // Boolean (0/1) + float -> Float, regardless of order
+        let (dt, _) = detect_data_type(&["0", "1", "2.5"], None);
+        assert_eq!(dt, DataType::Float);
+
+        let (dt, _) = detect_data_type(&["2.5", "0", "1"], None);
+        assert_eq!(dt, DataType::Float);
+    }
+
+    #[test]
+    fn test_string_boolean_float_downgrade() {
+        // Boolean (true/false) + float -> String
+        let (dt, _) = detect_data_type(&["true", "2.5"], None);
+        assert_eq!(dt, DataType::String);
+    }
 }
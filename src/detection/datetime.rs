@@ -1,4 +1,4 @@
-use crate::types::{DATE_PATTERNS, TIME_PATTERNS};
+use crate::types::{DateOrder, DateTimeGranularity, DATE_PATTERNS, TIME_PATTERNS};
 use chrono::NaiveDateTime;
 
 /// Date separator characters
@@ -7,6 +7,32 @@ const DATE_SEPS: &[char] = &['/', '-', '.'];
 /// Time separator characters
 const TIME_SEPS: &[char] = &[':'];
 
+/// English month-name date patterns (pattern, separator, chrono format),
+/// `mmm` for an abbreviated month and `mmmm` for a full one. Parsed apart
+/// from DATE_PATTERNS as the month is not numeric.
+const MONTH_NAME_PATTERNS: &[(&str, char, &str)] = &[
+    ("dd mmm yyyy", ' ', "%d %b %Y"),
+    ("mmmm dd, yyyy", ' ', "%B %d, %Y"),
+    ("dd-mmm-yyyy", '-', "%d-%b-%Y"),
+];
+
+/// English month names, matched case-insensitively on their full form or
+/// first three letters
+const MONTH_NAMES: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
 /// Minimum length of a RFC3339 date including time character
 /// e.g. 2020-01-01T
 const MIN_RFC3339_LEN: usize = 11;
@@ -35,6 +61,12 @@ impl DateTimePatterns {
                 .push((dp.pattern.to_string(), dp.separator));
         }
 
+        for (pattern, separator, _) in MONTH_NAME_PATTERNS {
+            patterns
+                .date_patterns
+                .push((pattern.to_string(), *separator));
+        }
+
         // Add time patterns
         for tp in TIME_PATTERNS {
             patterns
@@ -45,6 +77,17 @@ impl DateTimePatterns {
         patterns
     }
 
+    /// Move the remaining date patterns of the given order first, so it is
+    /// reported when the values could not rule out the other orders
+    pub fn prefer_date_order(&mut self, order: DateOrder) {
+        let wanted = order.as_pattern_order();
+        self.date_patterns.sort_by_key(|(pattern, _)| {
+            !DATE_PATTERNS
+                .iter()
+                .any(|dp| dp.pattern == pattern && dp.order == wanted)
+        });
+    }
+
     /// Get the best date pattern (first remaining)
     pub fn best_date_pattern(&self) -> Option<&str> {
         self.date_patterns.first().map(|(p, _)| p.as_str())
@@ -55,6 +98,17 @@ impl DateTimePatterns {
         self.time_patterns.first().map(|(p, _)| p.as_str())
     }
 
+    /// Whether the values matched carry a date, a time or both, from the
+    /// patterns that survived. RFC3339 values always carry both.
+    pub fn granularity(&self) -> Option<DateTimeGranularity> {
+        match (self.best_date_pattern(), self.best_time_pattern()) {
+            (Some("rfc3339"), _) | (Some(_), Some(_)) => Some(DateTimeGranularity::DateTime),
+            (Some(_), None) => Some(DateTimeGranularity::Date),
+            (None, Some(_)) => Some(DateTimeGranularity::Time),
+            (None, None) => None,
+        }
+    }
+
     /// Get combined datetime format string
     pub fn format_string(&self) -> Option<String> {
         match (self.best_date_pattern(), self.best_time_pattern()) {
@@ -70,6 +124,9 @@ pub fn could_be_datetime(value: &str) -> bool {
     if value.is_empty() {
         return false;
     }
+    if has_month_name(value) {
+        return value.chars().any(|c| c.is_ascii_digit());
+    }
 
     // Must contain date/time separator characters and digits
     let has_date_sep = value.chars().any(|c| DATE_SEPS.contains(&c));
@@ -89,6 +146,10 @@ pub fn guess_datetime_format(value: &str, patterns: &mut DateTimePatterns) -> bo
         return !patterns.date_patterns.is_empty();
     }
 
+    if has_month_name(value) {
+        return guess_month_name_format(value, patterns);
+    }
+
     // Detect separators in the value
     let date_sep = match value.chars().find(|c| DATE_SEPS.contains(c)) {
         Some(s) => s,
@@ -100,9 +161,9 @@ pub fn guess_datetime_format(value: &str, patterns: &mut DateTimePatterns) -> bo
     };
 
     // Filter date patterns by separator
-    patterns
-        .date_patterns
-        .retain(|(pattern, sep)| *sep == date_sep || pattern == "rfc3339");
+    patterns.date_patterns.retain(|(pattern, sep)| {
+        (*sep == date_sep || pattern == "rfc3339") && month_name_format(pattern).is_none()
+    });
 
     // Check RFC3339 format
     if patterns.date_patterns.iter().any(|(p, _)| p == "rfc3339") {
@@ -133,6 +194,39 @@ pub fn guess_datetime_format(value: &str, patterns: &mut DateTimePatterns) -> bo
     !patterns.date_patterns.is_empty()
 }
 
+/// Whether a word of the value is an English month name or its abbreviation
+pub fn has_month_name(value: &str) -> bool {
+    value
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| word.len() >= 3)
+        .any(|word| {
+            let word = word.to_lowercase();
+            MONTH_NAMES
+                .iter()
+                .any(|month| *month == word || month[..3] == word)
+        })
+}
+
+/// Chrono format of a month-name pattern, None for other patterns
+fn month_name_format(pattern: &str) -> Option<&'static str> {
+    MONTH_NAME_PATTERNS
+        .iter()
+        .find(|(p, _, _)| *p == pattern)
+        .map(|(_, _, format)| *format)
+}
+
+/// Keep the month-name patterns matching a value such as "15 Jan 2020" or
+/// "January 15, 2020". Such values have no time component.
+fn guess_month_name_format(value: &str, patterns: &mut DateTimePatterns) -> bool {
+    patterns.date_patterns.retain(|(pattern, _)| {
+        month_name_format(pattern)
+            .is_some_and(|format| chrono::NaiveDate::parse_from_str(value, format).is_ok())
+    });
+    patterns.time_patterns.clear();
+
+    !patterns.date_patterns.is_empty()
+}
+
 /// Check if value is RFC3339 format
 fn is_rfc3339(value: &str) -> bool {
     value.len() >= MIN_RFC3339_LEN
@@ -150,6 +244,9 @@ fn try_parse_date(value: &str, pattern: &str, date_sep: char, time_sep: Option<c
         value
     };
 
+    if !has_year_digits(date_part, pattern, date_sep) {
+        return false;
+    }
     let chrono_pattern = pattern_to_chrono(pattern, date_sep);
 
     NaiveDateTime::parse_from_str(
@@ -160,6 +257,16 @@ fn try_parse_date(value: &str, pattern: &str, date_sep: char, time_sep: Option<c
         || chrono::NaiveDate::parse_from_str(date_part, &chrono_pattern).is_ok()
 }
 
+/// Check that the year segment of a date has as many digits as the pattern
+/// asks for, as chrono reads "20" as the year 20 for `yyyy`
+fn has_year_digits(date_part: &str, pattern: &str, date_sep: char) -> bool {
+    pattern
+        .split(['-', '/', '.'])
+        .zip(date_part.split(date_sep))
+        .filter(|(token, _)| token.starts_with('y'))
+        .all(|(token, segment)| segment.len() == token.len())
+}
+
 /// Try to parse a time value with a specific pattern
 fn try_parse_time(value: &str, pattern: &str) -> bool {
     value
@@ -176,6 +283,7 @@ fn try_parse_time(value: &str, pattern: &str) -> bool {
 fn pattern_to_chrono(pattern: &str, sep: char) -> String {
     pattern
         .replace("yyyy", "%Y")
+        .replace("yy", "%y")
         .replace("mm", "%m")
         .replace("dd", "%d")
         .replace('-', &sep.to_string())
@@ -229,10 +337,90 @@ mod tests {
             .any(|(p, _)| p == "yyyy-mm-dd"));
     }
 
+    #[test]
+    fn test_prefer_date_order() {
+        // Ambiguous: both dd/mm and mm/dd remain, the preference decides
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("01/02/2020", &mut patterns));
+        assert_eq!(patterns.best_date_pattern(), Some("dd/mm/yyyy"));
+        patterns.prefer_date_order(DateOrder::Mdy);
+        assert_eq!(patterns.best_date_pattern(), Some("mm/dd/yyyy"));
+
+        // Unambiguous: mm/dd was ruled out and the preference has no effect
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("13/02/2020", &mut patterns));
+        patterns.prefer_date_order(DateOrder::Mdy);
+        assert_eq!(patterns.best_date_pattern(), Some("dd/mm/yyyy"));
+    }
+
+    #[test]
+    fn test_two_digit_years() {
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("15/01/20", &mut patterns));
+        assert!(guess_datetime_format("28/02/21", &mut patterns));
+        assert_eq!(patterns.best_date_pattern(), Some("dd/mm/yy"));
+
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("01-15-20", &mut patterns));
+        assert_eq!(patterns.best_date_pattern(), Some("mm-dd-yy"));
+
+        // Four-digit years never match the two-digit patterns, nor the reverse
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("15/01/2020", &mut patterns));
+        assert_eq!(patterns.best_date_pattern(), Some("dd/mm/yyyy"));
+        assert!(!guess_datetime_format("15/01/20", &mut patterns));
+        assert!(!guess_datetime_format(
+            "15/01/202",
+            &mut DateTimePatterns::new()
+        ));
+    }
+
+    #[test]
+    fn test_month_name_dates() {
+        assert!(could_be_datetime("15 Jan 2020"));
+        assert!(!could_be_datetime("Jan"));
+
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("15 Jan 2020", &mut patterns));
+        assert!(guess_datetime_format("3 FEB 2021", &mut patterns));
+        assert_eq!(patterns.format_string().as_deref(), Some("dd mmm yyyy"));
+
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("January 15, 2020", &mut patterns));
+        assert!(guess_datetime_format("march 3, 2021", &mut patterns));
+        assert_eq!(patterns.format_string().as_deref(), Some("mmmm dd, yyyy"));
+
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("15-Jan-2020", &mut patterns));
+        assert_eq!(patterns.best_date_pattern(), Some("dd-mmm-yyyy"));
+        // Numeric dates don't fit a month-name column
+        assert!(!guess_datetime_format("15-01-2020", &mut patterns));
+
+        assert!(!guess_datetime_format(
+            "Jan 2020",
+            &mut DateTimePatterns::new()
+        ));
+    }
+
     #[test]
     fn test_guess_datetime_with_time() {
         let mut patterns = DateTimePatterns::new();
         assert!(guess_datetime_format("2020-01-15 10:30:00", &mut patterns));
         assert!(patterns.best_date_pattern().is_some());
     }
+
+    #[test]
+    fn test_granularity() {
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("2020-01-15", &mut patterns));
+        assert_eq!(patterns.granularity(), Some(DateTimeGranularity::Date));
+
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("2020-01-15 10:30", &mut patterns));
+        assert_eq!(patterns.granularity(), Some(DateTimeGranularity::DateTime));
+
+        let mut patterns = DateTimePatterns::new();
+        assert!(guess_datetime_format("2020-01-15T10:30:00Z", &mut patterns));
+        assert_eq!(patterns.granularity(), Some(DateTimeGranularity::DateTime));
+    }
 }
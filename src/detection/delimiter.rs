@@ -1,4 +1,5 @@
 use crate::detection::email::is_valid_email;
+use crate::detection::quote::closing_quote;
 use crate::types::constants::{
     EMAIL_DOMAIN_CHARS, EMAIL_LOCAL_CHARS, FIELD_DELIMS, FIELD_DELIM_PERCENT,
 };
@@ -17,6 +18,24 @@ pub fn detect_delimiter(lines: &[&str], text_sep: Option<char>) -> Option<char>
     detect_delimiter_by_frequency(lines, text_sep)
 }
 
+/// Delimiter declared by an Excel `sep=X` line (e.g. `sep=;`), optionally
+/// quoted
+pub fn parse_sep_directive(line: &str) -> Option<char> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let line = line
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .unwrap_or(line);
+    let mut chars = line
+        .get(..4)?
+        .eq_ignore_ascii_case("sep=")
+        .then(|| line[4..].chars())?;
+    match (chars.next(), chars.next()) {
+        (Some(delim), None) => Some(delim),
+        _ => None,
+    }
+}
+
 /// Detect delimiter based on characters adjacent to email addresses
 fn detect_delimiter_from_email(lines: &[&str]) -> Option<char> {
     for line in lines {
@@ -116,9 +135,16 @@ fn detect_delimiter_by_frequency(lines: &[&str], text_sep: char) -> Option<char>
         .map(|&d| (d, 0, 0)) // (delimiter, total_count, lines_present)
         .collect();
 
-    for line in lines {
+    // Count on the text outside well-formed quoted fields, so that stray
+    // quotes can't hide the rest of a line
+    let stripped: Vec<String> = lines
+        .iter()
+        .map(|line| strip_quoted_fields(line, text_sep))
+        .collect();
+
+    for line in &stripped {
         for stat in delim_stats.iter_mut() {
-            let count = count_delimiters(stat.0, line, text_sep);
+            let count = count_delimiters(stat.0, line, '\0', None);
             stat.1 += count;
             if count > 0 {
                 stat.2 += 1;
@@ -136,12 +162,56 @@ fn detect_delimiter_by_frequency(lines: &[&str], text_sep: char) -> Option<char>
         .map(|&(delim, _, _)| delim)
 }
 
-/// Count occurrences of a delimiter in a line, respecting text separators
-pub fn count_delimiters(delimiter: char, line: &str, text_sep: char) -> usize {
+/// Remove quoted fields from a line. A quoted field opens with `text_sep` at
+/// the start of the line or right after a candidate delimiter, and closes at
+/// the next single (not doubled) `text_sep`. Other quotes, like inch marks
+/// inside unquoted text, are kept as plain characters, as is a quote never
+/// closed.
+fn strip_quoted_fields(line: &str, text_sep: char) -> String {
+    if text_sep == '\0' {
+        return line.to_string();
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut stripped = String::with_capacity(line.len());
+    let mut field_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == text_sep && field_start {
+            if let Some(end) = closing_quote(&chars, i + 1, text_sep) {
+                i = end + 1;
+                field_start = false;
+                continue;
+            }
+        }
+        stripped.push(c);
+        field_start = is_field_delimiter(c);
+        i += 1;
+    }
+
+    stripped
+}
+
+/// Count occurrences of a delimiter in a line, respecting text separators.
+/// Characters following `escape_char` are neither counted nor toggle quoting.
+pub fn count_delimiters(
+    delimiter: char,
+    line: &str,
+    text_sep: char,
+    escape_char: Option<char>,
+) -> usize {
     let mut count = 0;
     let mut inside_text = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if Some(c) == escape_char {
+            chars.next();
+            continue;
+        }
 
-    for c in line.chars() {
         if text_sep != '\0' && c == text_sep {
             inside_text = !inside_text;
         }
@@ -176,15 +246,58 @@ mod tests {
         assert_eq!(detect_delimiter(&lines, None), Some('\t'));
     }
 
+    #[test]
+    fn test_detect_delimiter_with_irregular_quotes() {
+        // The inch marks open quoted text hiding the semicolons
+        let lines = vec![
+            r#"Monitor 24";199,99;"black, matte, 4k""#,
+            r#"Laptop 15";899,00;"silver, glossy, fhd""#,
+            r#"Tablet 10";349,50;"white, matte, hd""#,
+        ];
+        assert_eq!(detect_delimiter(&lines, Some('"')), Some(';'));
+    }
+
+    #[test]
+    fn test_parse_sep_directive() {
+        assert_eq!(parse_sep_directive("sep=;"), Some(';'));
+        assert_eq!(parse_sep_directive("SEP=,"), Some(','));
+        assert_eq!(parse_sep_directive("\"sep=|\""), Some('|'));
+        assert_eq!(parse_sep_directive("sep=\t"), Some('\t'));
+        assert_eq!(parse_sep_directive("sep="), None);
+        assert_eq!(parse_sep_directive("sep=;;"), None);
+        assert_eq!(parse_sep_directive("separator,email"), None);
+    }
+
+    #[test]
+    fn test_strip_quoted_fields() {
+        assert_eq!(strip_quoted_fields(r#""a,b",c;"d ""e"", f""#, '"'), ",c;");
+        // Quotes inside unquoted text and unclosed quotes are kept
+        assert_eq!(
+            strip_quoted_fields(r#"24" screen;"x,y";"open"#, '"'),
+            r#"24" screen;;"open"#
+        );
+        assert_eq!(strip_quoted_fields(r#""a,b""#, '\0'), r#""a,b""#);
+    }
+
     #[test]
     fn test_count_delimiters_with_quotes() {
         let line = r#""hello,world",test,value"#;
-        assert_eq!(count_delimiters(',', line, '"'), 2);
+        assert_eq!(count_delimiters(',', line, '"', None), 2);
     }
 
     #[test]
     fn test_count_delimiters_without_quotes() {
         let line = "hello,world,test";
-        assert_eq!(count_delimiters(',', line, '\0'), 2);
+        assert_eq!(count_delimiters(',', line, '\0', None), 2);
+    }
+
+    #[test]
+    fn test_count_delimiters_with_escape() {
+        let line = r"a\;b;c";
+        assert_eq!(count_delimiters(';', line, '"', Some('\\')), 1);
+        assert_eq!(count_delimiters(';', line, '"', None), 2);
+
+        assert_eq!(count_delimiters(',', r"a\\,b", '"', Some('\\')), 1);
+        assert_eq!(count_delimiters(',', r#""a\",b",c"#, '"', Some('\\')), 1);
     }
 }
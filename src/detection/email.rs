@@ -5,8 +5,22 @@ use regex::Regex;
 static EMAIL_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap());
 
+/// Caller-supplied email validation rules, the built-in checks apply when unset
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmailRules<'a> {
+    /// Regex used instead of the built-in `EMAIL_REGEX`
+    pub regex: Option<&'a Regex>,
+    /// Accepted top-level domains (e.g. "com"), any TLD when unset
+    pub allowed_tlds: Option<&'a [String]>,
+}
+
 /// Check if a string is a valid email address
 pub fn is_valid_email(email: &str) -> bool {
+    is_valid_email_with(email, &EmailRules::default())
+}
+
+/// Check if a string is a valid email address under the given rules
+pub fn is_valid_email_with(email: &str, rules: &EmailRules) -> bool {
     let email = email.trim();
 
     // Must have local part and domain
@@ -21,21 +35,29 @@ pub fn is_valid_email(email: &str) -> bool {
     }
 
     // Final Boss
-    EMAIL_REGEX.is_match(email)
+    if !rules.regex.unwrap_or(&EMAIL_REGEX).is_match(email) {
+        return false;
+    }
+
+    match rules.allowed_tlds {
+        Some(tlds) => {
+            let tld = email.rsplit('.').next().unwrap_or("");
+            tlds.iter()
+                .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(tld))
+        }
+        None => true,
+    }
 }
 
 /// Detect which column contains email addresses.
-/// Returns the column index (0-based) with the most valid emails.
+/// Returns the column index (0-based) with the most valid emails, only
+/// matching the header names when there are no rows.
 pub fn detect_email_column(
     rows: &[Vec<String>],
     header: Option<&[String]>,
     skip_header: bool,
+    rules: &EmailRules,
 ) -> Option<usize> {
-    let num_columns = rows.first()?.len();
-    if num_columns == 0 {
-        return None;
-    }
-
     // Check header first for "email" or "e-mail"
     if skip_header {
         if let Some(col) = header.and_then(|headers| {
@@ -48,12 +70,17 @@ pub fn detect_email_column(
         }
     }
 
+    let num_columns = rows.first()?.len();
+    if num_columns == 0 {
+        return None;
+    }
+
     // Count valid emails in each column
     let mut email_counts: Vec<usize> = vec![0; num_columns];
 
     for row in rows {
         for (col_idx, value) in row.iter().enumerate().take(num_columns) {
-            if !value.is_empty() && is_valid_email(value) {
+            if !value.is_empty() && is_valid_email_with(value, rules) {
                 email_counts[col_idx] += 1;
             }
         }
@@ -89,6 +116,55 @@ mod tests {
         assert!(!is_valid_email("john@example"));
     }
 
+    #[test]
+    fn test_custom_email_regex() {
+        let corporate = Regex::new(r"^[a-z]+\.[a-z]+@corp\.example$").unwrap();
+        let rules = EmailRules {
+            regex: Some(&corporate),
+            ..Default::default()
+        };
+
+        assert!(is_valid_email_with("john.doe@corp.example", &rules));
+        assert!(!is_valid_email_with("john@corp.example", &rules));
+        assert!(!is_valid_email_with("john.doe@gmail.com", &rules));
+        // Structural pre-checks still apply
+        assert!(!is_valid_email_with("@corp.example", &rules));
+
+        let rows = vec![
+            vec![
+                "john@gmail.com".to_string(),
+                "john.doe@corp.example".to_string(),
+            ],
+            vec![
+                "jane@gmail.com".to_string(),
+                "jane.doe@corp.example".to_string(),
+            ],
+            vec!["bob@gmail.com".to_string(), "Bob".to_string()],
+        ];
+        assert_eq!(
+            detect_email_column(&rows, None, false, &EmailRules::default()),
+            Some(0)
+        );
+        assert_eq!(detect_email_column(&rows, None, false, &rules), Some(1));
+    }
+
+    #[test]
+    fn test_allowed_tlds() {
+        let tlds = vec!["com".to_string(), ".org".to_string()];
+        let rules = EmailRules {
+            allowed_tlds: Some(&tlds),
+            ..Default::default()
+        };
+
+        assert!(is_valid_email_with("foo@bar.com", &rules));
+        assert!(is_valid_email_with("foo@bar.ORG", &rules));
+        assert!(!is_valid_email_with("foo@bar.invalidtld", &rules));
+        assert!(!is_valid_email_with("foo@bar.zzz", &rules));
+
+        // Without an allowlist any TLD passes
+        assert!(is_valid_email("foo@bar.zzz"));
+    }
+
     #[test]
     fn test_detect_email_column_by_header() {
         let rows = vec![
@@ -96,7 +172,24 @@ mod tests {
             vec!["Jane".to_string(), "jane@example.com".to_string()],
         ];
         let header = vec!["name".to_string(), "email".to_string()];
-        assert_eq!(detect_email_column(&rows, Some(&header), true), Some(1));
+        assert_eq!(
+            detect_email_column(&rows, Some(&header), true, &EmailRules::default()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_detect_email_column_header_only() {
+        let header = vec!["name".to_string(), "E-mail".to_string()];
+        assert_eq!(
+            detect_email_column(&[], Some(&header), true, &EmailRules::default()),
+            Some(1)
+        );
+        let header = vec!["name".to_string(), "address".to_string()];
+        assert_eq!(
+            detect_email_column(&[], Some(&header), true, &EmailRules::default()),
+            None
+        );
     }
 
     #[test]
@@ -105,7 +198,10 @@ mod tests {
             vec!["John".to_string(), "john@example.com".to_string()],
             vec!["Jane".to_string(), "jane@example.com".to_string()],
         ];
-        assert_eq!(detect_email_column(&rows, None, false), Some(1));
+        assert_eq!(
+            detect_email_column(&rows, None, false, &EmailRules::default()),
+            Some(1)
+        );
     }
 
     #[test]
@@ -114,6 +210,9 @@ mod tests {
             vec!["john@example.com".to_string(), "John".to_string()],
             vec!["jane@example.com".to_string(), "Jane".to_string()],
         ];
-        assert_eq!(detect_email_column(&rows, None, false), Some(0));
+        assert_eq!(
+            detect_email_column(&rows, None, false, &EmailRules::default()),
+            Some(0)
+        );
     }
 }
@@ -1,26 +1,58 @@
+use crate::detection::datatype::{detect_data_type, detect_value_type, BooleanState};
 use crate::detection::email::is_valid_email;
 use crate::types::constants::{EMAIL_DOMAIN_CHARS, EMAIL_LOCAL_CHARS};
-
-/// Detect whether the first line is a header.
-/// Returns true if the first line appears to be a header (not data).
-pub fn has_header(lines: &[&str], text_sep: char, delimiter: char) -> bool {
-    let first_line = match lines.first() {
-        Some(&line) => line,
+use crate::types::DataType;
+
+/// Detect whether the first row is a header.
+/// Returns true if the first row appears to be a header (not data): its
+/// values are plain text where the following rows hold emails, numbers or
+/// dates in the same columns. Without such a signal, leans toward no header.
+pub fn has_header(rows: &[Vec<String>]) -> bool {
+    let first_row = match rows.first() {
+        Some(row) => row,
         None => return true,
     };
 
-    // Check if first line contains a valid email address - if so, no header
-    if contains_valid_email(first_line) {
+    // If header has empty fields (e.g., ";;;"), consider it not a header
+    if first_row.iter().all(|value| value.trim().is_empty()) {
         return false;
     }
 
-    // If header has empty fields (e.g., ";;;"), consider it not a header
-    let has_content = first_line
-        .chars()
-        .any(|c| c != delimiter && c != text_sep && !c.is_whitespace());
+    // Check if first row contains a valid email address - if so, no header
+    if first_row.iter().any(|value| contains_valid_email(value)) {
+        return false;
+    }
 
-    // Default: assume has header
-    has_content
+    // Nothing to compare a lone row with, keep it as the header
+    let body = &rows[1..];
+    if body.is_empty() {
+        return true;
+    }
+
+    // Typed values in the first row make it data
+    let mut bool_state = BooleanState::default();
+    if first_row.iter().any(|value| {
+        !value.is_empty() && detect_value_type(value, &mut bool_state) != DataType::String
+    }) {
+        return false;
+    }
+
+    // A text first row over an email or typed column is a header
+    (0..first_row.len()).any(|col| {
+        let values: Vec<&str> = body
+            .iter()
+            .filter_map(|row| row.get(col).map(|v| v.as_str()))
+            .collect();
+        values.iter().any(|value| contains_valid_email(value))
+            || (!values.is_empty() && detect_data_type(&values, None).0 != DataType::String)
+    })
+}
+
+/// Detect a units/metadata row (e.g. `kg,m`) right after the header.
+/// `rows` starts with the header; the second row is a units row when it is
+/// plain text over typed or email columns, like a header over its body.
+pub fn has_units_row(rows: &[Vec<String>]) -> bool {
+    rows.len() > 2 && has_header(&rows[1..])
 }
 
 /// Check if a line contains a valid email address
@@ -66,22 +98,77 @@ fn contains_valid_email(line: &str) -> bool {
 mod tests {
     use super::*;
 
+    fn rows(lines: &[&str], delimiter: char) -> Vec<Vec<String>> {
+        lines
+            .iter()
+            .map(|line| line.split(delimiter).map(|v| v.to_string()).collect())
+            .collect()
+    }
+
     #[test]
     fn test_header_no_email() {
-        let lines = vec!["email,name,country", "john@example.com,John,US"];
-        assert!(has_header(&lines, '"', ','));
+        let rows = rows(&["email,name,country", "john@example.com,John,US"], ',');
+        assert!(has_header(&rows));
     }
 
     #[test]
     fn test_no_header_has_email() {
-        let lines = vec!["john@example.com,John,US", "jane@example.com,Jane,UK"];
-        assert!(!has_header(&lines, '"', ','));
+        let rows = rows(
+            &["john@example.com,John,US", "jane@example.com,Jane,UK"],
+            ',',
+        );
+        assert!(!has_header(&rows));
     }
 
     #[test]
     fn test_empty_header() {
-        let lines = vec![";;;", "john@example.com;John;US"];
-        assert!(!has_header(&lines, '"', ';'));
+        let rows = rows(&[";;;", "john@example.com;John;US"], ';');
+        assert!(!has_header(&rows));
+    }
+
+    #[test]
+    fn test_header_by_type_signature() {
+        // A text row over numeric data is a header
+        let rows = rows(
+            &["product,price,stock", "Widget,9.99,12", "Gadget,19.50,3"],
+            ',',
+        );
+        assert!(has_header(&rows));
+    }
+
+    #[test]
+    fn test_header_over_dates() {
+        let rows = rows(&["name,joined", "Ann,2020-01-15", "Bob,2021-03-02"], ',');
+        assert!(has_header(&rows));
+    }
+
+    #[test]
+    fn test_no_header_by_type_signature() {
+        // A first row typed like the body is data
+        let rows = rows(&["Widget,9.99,12", "Gadget,19.50,3"], ',');
+        assert!(!has_header(&rows));
+    }
+
+    #[test]
+    fn test_no_header_all_strings() {
+        // All-text data gives no signal, the first row is kept as data
+        let rows = rows(
+            &["John,Smith,London", "Jane,Doe,Paris", "Bob,Jones,Rome"],
+            ',',
+        );
+        assert!(!has_header(&rows));
+    }
+
+    #[test]
+    fn test_units_row() {
+        let with_units = rows(&["mass,length", "kg,m", "1.5,20", "2.25,31"], ',');
+        assert!(has_units_row(&with_units));
+
+        let without_units = rows(&["mass,length", "1.5,20", "2.25,31"], ',');
+        assert!(!has_units_row(&without_units));
+
+        // Nothing follows the candidate row
+        assert!(!has_units_row(&with_units[..2]));
     }
 
     #[test]
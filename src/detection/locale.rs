@@ -0,0 +1,100 @@
+use crate::detection::DateTimePatterns;
+use crate::types::DATE_PATTERNS;
+
+/// Decimal separator used by the values of a numeric column: the most
+/// common of `.` and `,` among values with a single separator, None when no
+/// value has a fractional part
+pub fn detect_decimal_separator(values: &[&str]) -> Option<char> {
+    let (mut dots, mut commas) = (0, 0);
+
+    for value in values {
+        let value = value.trim();
+        let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+        let mut separators = digits.chars().filter(|c| !c.is_ascii_digit());
+        let sep = match (separators.next(), separators.next()) {
+            (Some(sep), None) => sep,
+            _ => continue,
+        };
+        // Digits on both sides, e.g. "1.5" but not "1." or ".5"
+        if digits.starts_with(sep) || digits.ends_with(sep) {
+            continue;
+        }
+        match sep {
+            '.' => dots += 1,
+            ',' => commas += 1,
+            _ => {}
+        }
+    }
+
+    match (dots, commas) {
+        (0, 0) => None,
+        _ if commas > dots => Some(','),
+        _ => Some('.'),
+    }
+}
+
+/// Suggest a plausible locale from the date pattern retained for a column
+/// and the decimal separator of numeric columns. Returns None when the
+/// evidence is missing or contradictory (e.g. `m/d/y` dates with `,`
+/// decimals).
+pub fn guess_locale(
+    patterns: Option<&DateTimePatterns>,
+    decimal_separator: Option<char>,
+) -> Option<&'static str> {
+    let date_pattern = patterns
+        .and_then(DateTimePatterns::best_date_pattern)
+        .and_then(|best| DATE_PATTERNS.iter().find(|dp| dp.pattern == best));
+
+    match (
+        date_pattern.map(|dp| (dp.order, dp.separator)),
+        decimal_separator,
+    ) {
+        (Some(("m/d/y", _)), Some('.') | None) => Some("en_US"),
+        (Some(("d/m/y", '.')), Some(',')) => Some("de_DE"),
+        (Some(("d/m/y", _)), Some(',')) => Some("fr_FR"),
+        (Some(("d/m/y", _)), Some('.')) => Some("en_GB"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detection::guess_datetime_format;
+
+    fn patterns_for(values: &[&str]) -> DateTimePatterns {
+        let mut patterns = DateTimePatterns::new();
+        for value in values {
+            assert!(guess_datetime_format(value, &mut patterns));
+        }
+        patterns
+    }
+
+    #[test]
+    fn test_detect_decimal_separator() {
+        assert_eq!(detect_decimal_separator(&["1.5", "2", "-3.25"]), Some('.'));
+        assert_eq!(detect_decimal_separator(&["1,5", "2,75", "3"]), Some(','));
+        assert_eq!(detect_decimal_separator(&["1", "2", ""]), None);
+        assert_eq!(detect_decimal_separator(&["1.234,5", "7."]), None);
+    }
+
+    #[test]
+    fn test_guess_locale() {
+        let us = patterns_for(&["12/31/2020", "01/15/2021"]);
+        assert_eq!(guess_locale(Some(&us), Some('.')), Some("en_US"));
+        assert_eq!(guess_locale(Some(&us), None), Some("en_US"));
+        assert_eq!(guess_locale(Some(&us), Some(',')), None);
+
+        let fr = patterns_for(&["31/12/2020", "15/01/2021"]);
+        assert_eq!(guess_locale(Some(&fr), Some(',')), Some("fr_FR"));
+        assert_eq!(guess_locale(Some(&fr), Some('.')), Some("en_GB"));
+        assert_eq!(guess_locale(Some(&fr), None), None);
+
+        let de = patterns_for(&["31.12.2020"]);
+        assert_eq!(guess_locale(Some(&de), Some(',')), Some("de_DE"));
+
+        let iso = patterns_for(&["2020-12-31"]);
+        assert_eq!(guess_locale(Some(&iso), Some('.')), None);
+        assert_eq!(guess_locale(None, Some(',')), None);
+    }
+}
@@ -1,15 +1,30 @@
 pub mod charset;
+pub mod currency;
 pub mod datatype;
 pub mod datetime;
 pub mod delimiter;
 pub mod email;
 pub mod header;
+pub mod locale;
+pub mod percent;
+pub mod phone;
 pub mod quote;
+pub mod semantic;
+pub mod url;
 
+pub use crate::analyzer::detect_all;
 pub use charset::detect_charset;
-pub use datatype::detect_data_type;
-pub use datetime::{could_be_datetime, guess_datetime_format, DateTimePatterns};
-pub use delimiter::detect_delimiter;
-pub use email::detect_email_column;
-pub use header::has_header;
+pub use currency::detect_currency_column;
+pub use datatype::{
+    detect_column_types, detect_data_type, normalize_fullwidth_digits, ColumnTypeState,
+};
+pub use datetime::{could_be_datetime, guess_datetime_format, has_month_name, DateTimePatterns};
+pub use delimiter::{detect_delimiter, parse_sep_directive};
+pub use email::{detect_email_column, EmailRules};
+pub use header::{has_header, has_units_row};
+pub use locale::{detect_decimal_separator, guess_locale};
+pub use percent::is_percent_column;
+pub use phone::is_phone_column;
 pub use quote::detect_quote_char;
+pub use semantic::{detect_semantic_type, ColumnTypeDetector};
+pub use url::is_url_column;
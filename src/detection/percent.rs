@@ -0,0 +1,52 @@
+/// Return the numeric part of a percentage value (e.g. "12.5" for "12.5%")
+pub fn strip_percent(value: &str) -> Option<&str> {
+    let number = value.trim().strip_suffix('%')?.trim_end();
+    if number.is_empty() {
+        return None;
+    }
+
+    // Handle both . and , as decimal separators
+    number.replace(',', ".").parse::<f64>().ok().map(|_| number)
+}
+
+/// Check whether a column holds percentages.
+/// Every non-empty value must carry the '%' sign, so free text that merely
+/// mentions a percentage is not matched.
+pub fn is_percent_column(values: &[&str]) -> bool {
+    let mut seen = false;
+
+    for value in values {
+        if value.trim().is_empty() {
+            continue;
+        }
+        if strip_percent(value).is_none() {
+            return false;
+        }
+        seen = true;
+    }
+
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_percent() {
+        assert_eq!(strip_percent("12%"), Some("12"));
+        assert_eq!(strip_percent("3.5%"), Some("3.5"));
+        assert_eq!(strip_percent("-2,5 %"), Some("-2,5"));
+        assert_eq!(strip_percent("%"), None);
+        assert_eq!(strip_percent("12"), None);
+        assert_eq!(strip_percent("about 5%"), None);
+    }
+
+    #[test]
+    fn test_percent_column() {
+        assert!(is_percent_column(&["12%", "3.5%", "", "100%"]));
+        assert!(!is_percent_column(&["12%", "3.5", "100%"]));
+        assert!(!is_percent_column(&["up 5%", "down 2%"]));
+        assert!(!is_percent_column(&["", " "]));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::types::constants::{PHONE_COLUMN_PERCENT, PHONE_MAX_DIGITS, PHONE_MIN_DIGITS};
+
+/// Separators accepted between the digit groups of a phone number,
+/// besides parentheses around the area code
+const PHONE_SEPARATORS: &[char] = &[' ', '-', '.'];
+
+/// Check whether a value looks like a phone number, e.g. "+1 555-123-4567",
+/// "0044 20 7946 0000" or "(555) 123.4567".
+/// Conservative: only digits and separators, an optional leading '+',
+/// balanced parentheses, PHONE_MIN_DIGITS to PHONE_MAX_DIGITS digits, and
+/// either an international prefix or separated digit groups, so that plain
+/// numbers are not matched, nor IP addresses and version numbers.
+pub fn is_phone_like(value: &str) -> bool {
+    let value = value.trim();
+    let (international, number) = match value.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (value.starts_with("00"), value),
+    };
+
+    let mut digits = 0;
+    let mut separated = false;
+    let mut open_paren = false;
+    let mut seen_paren = false;
+    for c in number.chars() {
+        match c {
+            '0'..='9' => digits += 1,
+            '(' if !seen_paren => {
+                open_paren = true;
+                seen_paren = true;
+            }
+            ')' if open_paren => open_paren = false,
+            c if PHONE_SEPARATORS.contains(&c) => separated = true,
+            _ => return false,
+        }
+    }
+
+    !open_paren
+        && (PHONE_MIN_DIGITS..=PHONE_MAX_DIGITS).contains(&digits)
+        && number.starts_with(|c: char| c.is_ascii_digit() || c == '(')
+        && (international || separated || seen_paren)
+        && (international || seen_paren || !is_dotted_numbers(number))
+}
+
+/// Digit groups separated by dots only, all of at most 3 digits or some of
+/// a single one, like "192.168.0.1" or "10.0.19041.1"
+fn is_dotted_numbers(value: &str) -> bool {
+    if value.contains(|c: char| c != '.' && PHONE_SEPARATORS.contains(&c)) {
+        return false;
+    }
+    let groups: Vec<&str> = value.split('.').collect();
+    groups.len() > 1 && (groups.iter().all(|g| g.len() <= 3) || groups.iter().any(|g| g.len() == 1))
+}
+
+/// Check whether a column holds phone numbers: at least PHONE_COLUMN_PERCENT
+/// of its non-empty values look like one
+pub fn is_phone_column(values: &[&str]) -> bool {
+    let mut total = 0;
+    let mut phones = 0;
+    for value in values.iter().filter(|v| !v.trim().is_empty()) {
+        total += 1;
+        if is_phone_like(value) {
+            phones += 1;
+        }
+    }
+    total > 0 && phones * 100 / total >= PHONE_COLUMN_PERCENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phone_formats() {
+        assert!(is_phone_like("+1 555-123-4567"));
+        assert!(is_phone_like("0044 20 7946 0000"));
+        assert!(is_phone_like("+33612345678"));
+        assert!(is_phone_like("(555) 123-4567"));
+        assert!(is_phone_like("555.123.4567"));
+        assert!(is_phone_like("+44 (0) 20 7946 0000"));
+        assert!(is_phone_like(" 06 12 34 56 78 "));
+    }
+
+    #[test]
+    fn test_not_phone() {
+        // Plain numbers, too few or too many digits
+        assert!(!is_phone_like("5551234567"));
+        assert!(!is_phone_like("12-34"));
+        assert!(!is_phone_like("+1 234 567 890 123 456 7"));
+        // Letters, misplaced signs, unbalanced parentheses
+        assert!(!is_phone_like("555-CALL-NOW"));
+        assert!(!is_phone_like("555+123-4567"));
+        assert!(!is_phone_like("(555 123-4567"));
+        assert!(!is_phone_like("555) 123-4567"));
+        assert!(!is_phone_like("-555 123 4567"));
+        assert!(!is_phone_like(""));
+    }
+
+    #[test]
+    fn test_not_phone_dotted() {
+        // IP addresses
+        assert!(!is_phone_like("192.168.0.1"));
+        assert!(!is_phone_like("10.20.30.40"));
+        assert!(!is_phone_like("172.16.254.1"));
+        // Version numbers
+        assert!(!is_phone_like("10.0.19041.1"));
+        assert!(!is_phone_like("2024.01.15.3"));
+        assert!(!is_phone_like("1.12.345.67"));
+        // Unless prefixed like a phone number
+        assert!(is_phone_like("+33.6.12.34.56.78"));
+        assert!(is_phone_like("(555) 123.4567"));
+        assert!(!is_phone_column(&[
+            "192.168.0.1",
+            "10.20.30.40",
+            "172.16.254.1"
+        ]));
+    }
+
+    #[test]
+    fn test_phone_column() {
+        assert!(is_phone_column(&[
+            "+1 555-123-4567",
+            "",
+            "0044 20 7946 0000",
+            "(555) 123-4567",
+            "+33 6 12 34 56 78",
+            "n/a",
+        ]));
+        assert!(!is_phone_column(&["+1 555-123-4567", "John", "Jane"]));
+        assert!(!is_phone_column(&["", " "]));
+    }
+}
@@ -1,47 +1,89 @@
-use crate::types::constants::{TEXT_SEPS, TEXT_SEP_PERCENT};
+use crate::types::constants::{FIELD_DELIMS, TEXT_SEPS};
 
 /// Detect the text/quote separator character.
-/// Returns the quote character that appears in pairs across most lines.
-pub fn detect_quote_char(lines: &[&str]) -> Option<char> {
+/// Returns the quote character wrapping fields in the most lines, present in
+/// at least `min_percent` of them. Lines without quotes are neutral, while a
+/// quoted field left open disqualifies the character.
+pub fn detect_quote_char(lines: &[&str], min_percent: usize) -> Option<char> {
     if lines.is_empty() {
         return None;
     }
 
-    // Track stats for each candidate: (char, total_count, lines_with_pairs)
-    let mut sep_stats: Vec<(char, usize, usize)> = TEXT_SEPS.iter().map(|&c| (c, 0, 0)).collect();
+    // Track stats for each candidate: (char, quoted_fields, lines_with_fields, disqualified)
+    let mut sep_stats: Vec<(char, usize, usize, bool)> =
+        TEXT_SEPS.iter().map(|&c| (c, 0, 0, false)).collect();
 
     for line in lines {
-        for stat in sep_stats.iter_mut() {
-            let count = line.chars().filter(|&ch| ch == stat.0).count();
-
-            // Must be even (pairs) to be a valid text separator
-            if !count.is_multiple_of(2) {
-                // Invalid - reset counts for this separator
-                stat.1 = 0;
-                stat.2 = 0;
-                break;
-            }
-
-            stat.1 += count;
-            if count > 0 {
-                stat.2 += 1;
+        for stat in sep_stats.iter_mut().filter(|stat| !stat.3) {
+            match count_quoted_fields(line, stat.0) {
+                Some(0) => {}
+                Some(count) => {
+                    stat.1 += count;
+                    stat.2 += 1;
+                }
+                None => stat.3 = true,
             }
         }
     }
 
-    // Find separator with highest count that appears in enough lines
+    // Find separator with most quoted fields that appears in enough lines
     sep_stats
         .iter()
-        .filter(|&&(_, total, lines_present)| {
-            total > 0 && (lines_present * 100 / lines.len()) >= TEXT_SEP_PERCENT
+        .filter(|&&(_, total, lines_present, disqualified)| {
+            !disqualified && total > 0 && (lines_present * 100 / lines.len()) >= min_percent
         })
-        .max_by_key(|&&(_, total, _)| total)
-        .map(|&(sep, _, _)| sep)
+        .max_by_key(|&&(_, total, _, _)| total)
+        .map(|&(sep, _, _, _)| sep)
+}
+
+/// Number of quoted fields in a line. A quoted field opens with `quote` at
+/// the start of the line or after a candidate delimiter (spaces allowed) and
+/// closes at the next single quote. Other quotes, like an apostrophe inside
+/// unquoted text, are plain characters. None when a quoted field is never
+/// closed.
+fn count_quoted_fields(line: &str, quote: char) -> Option<usize> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut count = 0;
+    let mut field_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == quote && field_start {
+            i = closing_quote(&chars, i + 1, quote)? + 1;
+            count += 1;
+            field_start = false;
+            continue;
+        }
+        if c != ' ' {
+            field_start = FIELD_DELIMS.contains(&c);
+        }
+        i += 1;
+    }
+
+    Some(count)
+}
+
+/// Index of the quote closing a quoted field, skipping doubled quotes
+pub fn closing_quote(chars: &[char], from: usize, text_sep: char) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == text_sep {
+            if chars.get(i + 1) == Some(&text_sep) {
+                i += 2;
+                continue;
+            }
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::constants::TEXT_SEP_PERCENT;
 
     #[test]
     fn test_detect_double_quote() {
@@ -49,7 +91,7 @@ mod tests {
             r#""John","Doe","john@example.com""#,
             r#""Jane","Doe","jane@example.com""#,
         ];
-        assert_eq!(detect_quote_char(&lines), Some('"'));
+        assert_eq!(detect_quote_char(&lines, TEXT_SEP_PERCENT), Some('"'));
     }
 
     #[test]
@@ -58,19 +100,55 @@ mod tests {
             "'John','Doe','john@example.com'",
             "'Jane','Doe','jane@example.com'",
         ];
-        assert_eq!(detect_quote_char(&lines), Some('\''));
+        assert_eq!(detect_quote_char(&lines, TEXT_SEP_PERCENT), Some('\''));
     }
 
     #[test]
     fn test_no_quotes() {
         let lines = vec!["John,Doe,john@example.com", "Jane,Doe,jane@example.com"];
-        assert_eq!(detect_quote_char(&lines), None);
+        assert_eq!(detect_quote_char(&lines, TEXT_SEP_PERCENT), None);
     }
 
     #[test]
     fn test_unbalanced_quotes() {
         // Odd number of quotes - not valid
         let lines = vec![r#""John,Doe,john@example.com"#];
-        assert_eq!(detect_quote_char(&lines), None);
+        assert_eq!(detect_quote_char(&lines, TEXT_SEP_PERCENT), None);
+    }
+
+    #[test]
+    fn test_quote_percent_threshold() {
+        // Quotes on 1 line out of 4
+        let lines = vec![
+            r#""Doe, John",john@example.com"#,
+            "Jane,jane@example.com",
+            "Jim,jim@example.com",
+            "Joe,joe@example.com",
+        ];
+        assert_eq!(detect_quote_char(&lines, TEXT_SEP_PERCENT), None);
+        assert_eq!(detect_quote_char(&lines, 25), Some('"'));
+        assert_eq!(detect_quote_char(&lines, 26), None);
+    }
+
+    #[test]
+    fn test_occasionally_quoted_fields() {
+        // Inch marks and apostrophes inside unquoted text, quoted fields
+        // only here and there
+        let lines = vec![
+            "email,product,note",
+            r#"jane@example.com,"Desk, oak",O'Brien's"#,
+            r#"jim@example.com,Lamp,"fragile, top""#,
+            r#"john@example.com,Monitor 24",ok"#,
+            "joe@example.com,Chair,none",
+            r#"ann@example.com,"Shelf, 3 levels",none"#,
+        ];
+        assert_eq!(detect_quote_char(&lines, TEXT_SEP_PERCENT), Some('"'));
+    }
+
+    #[test]
+    fn test_count_quoted_fields() {
+        assert_eq!(count_quoted_fields(r#"a,"b, c", "d""e""#, '"'), Some(2));
+        assert_eq!(count_quoted_fields(r#"24" screen,x"#, '"'), Some(0));
+        assert_eq!(count_quoted_fields(r#"a,"open"#, '"'), None);
     }
 }
@@ -0,0 +1,60 @@
+use std::fmt::Debug;
+
+/// Detector for a domain-specific column type, e.g. country codes or SKUs.
+/// Registered through `Config::type_detectors`, detectors get a chance to
+/// claim a column before the built-in type detection.
+pub trait ColumnTypeDetector: Debug + Send + Sync {
+    /// Return the semantic type name if the column values belong to it
+    fn detect(&self, values: &[&str]) -> Option<String>;
+}
+
+/// Ask each detector in order, the first one claiming the column wins
+pub fn detect_semantic_type<D>(values: &[&str], detectors: &[D]) -> Option<String>
+where
+    D: AsRef<dyn ColumnTypeDetector>,
+{
+    if values.iter().all(|v| v.trim().is_empty()) {
+        return None;
+    }
+    detectors
+        .iter()
+        .find_map(|detector| detector.as_ref().detect(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct Fixed(&'static str, Option<&'static str>);
+
+    impl ColumnTypeDetector for Fixed {
+        fn detect(&self, values: &[&str]) -> Option<String> {
+            values
+                .iter()
+                .all(|v| v.starts_with(self.0))
+                .then(|| self.1.map(String::from))
+                .flatten()
+        }
+    }
+
+    #[test]
+    fn test_detect_semantic_type() {
+        let detectors: Vec<Arc<dyn ColumnTypeDetector>> = vec![
+            Arc::new(Fixed("SKU-", None)),
+            Arc::new(Fixed("SKU-", Some("sku"))),
+            Arc::new(Fixed("SK", Some("other"))),
+        ];
+        assert_eq!(
+            detect_semantic_type(&["SKU-1", "SKU-2"], &detectors),
+            Some("sku".to_string())
+        );
+        assert_eq!(
+            detect_semantic_type(&["SK1"], &detectors),
+            Some("other".to_string())
+        );
+        assert_eq!(detect_semantic_type(&["abc"], &detectors), None);
+        assert_eq!(detect_semantic_type(&["", " "], &detectors), None);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::types::constants::URL_COLUMN_PERCENT;
+
+/// Check whether a value looks like an http(s) URL, e.g.
+/// "https://example.com/about?lang=en". Only the scheme and the host are
+/// checked: dot-separated labels of letters, digits and '-', an optional
+/// port, no whitespace.
+pub fn is_url_like(value: &str) -> bool {
+    let value = value.trim();
+    if value.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let lower = value.to_ascii_lowercase();
+    let rest = match lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => host,
+        Some(_) => return false,
+        None => authority,
+    };
+
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    };
+    (host == "localhost" || host.contains('.')) && host.split('.').all(valid_label)
+}
+
+/// Check whether a column holds URLs: at least URL_COLUMN_PERCENT of its
+/// non-empty values look like one
+pub fn is_url_column(values: &[&str]) -> bool {
+    let mut total = 0;
+    let mut urls = 0;
+    for value in values.iter().filter(|v| !v.trim().is_empty()) {
+        total += 1;
+        if is_url_like(value) {
+            urls += 1;
+        }
+    }
+    total > 0 && urls * 100 / total >= URL_COLUMN_PERCENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_formats() {
+        assert!(is_url_like("https://example.com"));
+        assert!(is_url_like("http://www.example.co.uk/about?lang=en#team"));
+        assert!(is_url_like("HTTPS://Example.com:8443/"));
+        assert!(is_url_like("http://localhost:3000"));
+        assert!(is_url_like(" https://192.168.0.1/status "));
+    }
+
+    #[test]
+    fn test_not_url() {
+        assert!(!is_url_like("example.com"));
+        assert!(!is_url_like("ftp://example.com"));
+        assert!(!is_url_like("https://"));
+        assert!(!is_url_like("https://example"));
+        assert!(!is_url_like("https://exa mple.com"));
+        assert!(!is_url_like("https://-example.com"));
+        assert!(!is_url_like("https://example..com"));
+        assert!(!is_url_like("https://example.com:port"));
+        assert!(!is_url_like("see https://example.com"));
+    }
+
+    #[test]
+    fn test_url_column() {
+        assert!(is_url_column(&[
+            "https://example.com",
+            "",
+            "http://example.org/contact",
+            "https://shop.example.net"
+        ]));
+        assert!(!is_url_column(&[
+            "Visit our site",
+            "example.com",
+            "https://example.com"
+        ]));
+        assert!(!is_url_column(&["", " "]));
+    }
+}
@@ -6,6 +6,10 @@ pub enum CsvAnalyzerError {
     #[error("CSV error: {0}")]
     CsvError(CsvErrorType),
 
+    /// CSV error with details for the internal message
+    #[error("CSV error: {0} ({1})")]
+    CsvErrorDetail(CsvErrorType, String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -23,6 +27,7 @@ impl CsvAnalyzerError {
     pub fn error_type(&self) -> CsvErrorType {
         match self {
             CsvAnalyzerError::CsvError(t) => *t,
+            CsvAnalyzerError::CsvErrorDetail(t, _) => *t,
             CsvAnalyzerError::IoError(_) => CsvErrorType::Process,
             CsvAnalyzerError::DatabaseError(_) => CsvErrorType::Database,
             CsvAnalyzerError::ConfigError(_) => CsvErrorType::Process,
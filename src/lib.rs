@@ -1,13 +1,21 @@
 pub mod analyzer;
 pub mod config;
 pub mod db;
+pub mod debug;
 pub mod detection;
 pub mod error;
 pub mod output;
+pub mod stats;
 pub mod types;
 pub mod validation;
 
-pub use analyzer::CsvAnalyzer;
-pub use config::{Config, DbConfig};
+pub use analyzer::{detect_all, CsvAnalyzer};
+pub use config::{Config, DbConfig, ParseProfile};
+pub use detection::ColumnTypeDetector;
 pub use error::{CsvAnalyzerError, Result};
-pub use types::{CsvErrorType, DataType};
+pub use output::{Emit, OutputFormat};
+pub use types::{
+    ColumnStats, ContactProperty, CsvErrorType, DataType, DataTypeName, DateOrder,
+    DateTimeGranularity, DetectionReport, DuplicateRows, IdSequence, NumberFormat, StageTimings,
+    Warning, WarningKind,
+};
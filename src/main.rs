@@ -1,5 +1,6 @@
 use clap::Parser;
-use csvanalyzertool::{Config, CsvAnalyzer, DbConfig};
+use csvanalyzertool::{Config, CsvAnalyzer, DateOrder, DbConfig, Emit, OutputFormat};
+use std::io::{self, Write};
 use std::path::Path;
 
 /// CSV Analyzer - Analyze CSV files for contact import
@@ -20,13 +21,26 @@ struct Args {
     locale: String,
 
     /// Path to CSV file
-    #[arg(short = 'f', long = "filename", required = true)]
-    filename: String,
+    #[arg(
+        short = 'f',
+        long = "filename",
+        required_unless_present = "data_base64"
+    )]
+    filename: Option<String>,
+
+    /// CSV content encoded in base64, analyzed instead of a file
+    #[arg(long = "data-base64", conflicts_with = "filename")]
+    data_base64: Option<String>,
 
     /// Path to config file (default: /etc/mailjet.conf)
     #[arg(short = 'c', long = "config")]
     config_file: Option<String>,
 
+    /// Path to analyzer options file (.toml or .json), overridden by CLI
+    /// arguments; switches without a --no- counterpart can only turn an option on
+    #[arg(long = "options")]
+    options_file: Option<String>,
+
     /// PostgreSQL host (overrides config file and env vars)
     #[arg(long = "db-host")]
     db_host: Option<String>,
@@ -43,34 +57,262 @@ struct Args {
     #[arg(long = "db-user")]
     db_user: Option<String>,
 
-    /// PostgreSQL password (can also use PGPASSWORD env var)
+    /// PostgreSQL password (can also use PGPASSWORD env var, or PGPASSFILE / ~/.pgpass)
     #[arg(long = "db-password")]
     db_password: Option<String>,
 
+    /// File holding the PostgreSQL password, read at connection time
+    #[arg(long = "db-password-file", conflicts_with = "db_password")]
+    db_password_file: Option<String>,
+
+    /// SQLite file to read contact metadata from instead of PostgreSQL
+    #[arg(long = "sqlite-db")]
+    sqlite_db: Option<String>,
+
+    /// Contact metadata table, "{akid}" is replaced with the account id (default: contact_meta)
+    #[arg(long = "contact-meta-table")]
+    contact_meta_table: Option<String>,
+
+    /// PostgreSQL connection timeout in seconds
+    #[arg(long = "db-connect-timeout")]
+    db_connect_timeout: Option<u64>,
+
+    /// Number of PostgreSQL connection retries (default: 0)
+    #[arg(long = "db-retries")]
+    db_retries: Option<u32>,
+
+    /// Number of leading lines (e.g. a report title) to discard before the header
+    #[arg(long = "skip-lines")]
+    skip_lines: Option<usize>,
+
+    /// Character starting comment lines to leave out (e.g. "#")
+    #[arg(long = "comment-prefix")]
+    comment_prefix: Option<char>,
+
     /// Number of lines to scan (default: 1000)
     #[arg(long = "scan-lines")]
     scan_lines: Option<usize>,
 
+    /// Maximum number of bytes to read for the sample (default: 51200)
+    #[arg(long = "sample-bytes")]
+    sample_bytes: Option<usize>,
+
     /// Number of data rows to return (default: 10)
     #[arg(long = "return-lines")]
     return_lines: Option<usize>,
+
+    /// Number of data rows skipped before the returned ones (default: 0)
+    #[arg(long = "return-offset")]
+    return_offset: Option<usize>,
+
+    /// Output format
+    #[arg(long = "format", value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Report data types by name (e.g. "Integer") instead of code
+    #[arg(long = "type-names")]
+    type_names: bool,
+
+    /// Fail when no email column is found, even if the options file allows it
+    #[arg(long = "require-email", conflicts_with = "no_require_email")]
+    require_email: bool,
+
+    /// Don't fail when no email column is found (generic CSV profiling)
+    #[arg(long = "no-require-email")]
+    no_require_email: bool,
+
+    /// Detect column types, even if the options file turns it off
+    #[arg(long = "infer-types", conflicts_with = "no_infer_types")]
+    infer_types: bool,
+
+    /// Report every column as String, without type detection
+    #[arg(long = "no-infer-types")]
+    no_infer_types: bool,
+
+    /// Regex replacing the built-in email validation
+    #[arg(long = "email-regex")]
+    email_regex: Option<regex::Regex>,
+
+    /// Comma-separated email top-level domains to accept (e.g. "com,org")
+    #[arg(long = "allowed-tlds", value_delimiter = ',')]
+    allowed_tlds: Option<Vec<String>>,
+
+    /// Escape character for delimiters in unquoted fields (e.g. "\\")
+    #[arg(long = "escape-char")]
+    escape_char: Option<char>,
+
+    /// Treat full-width digits (e.g. "１２３") as numbers during type detection
+    #[arg(long = "normalize-fullwidth")]
+    normalize_fullwidth: bool,
+
+    /// Date order reported when values fit several orders (e.g. 01/02/2020)
+    #[arg(long = "prefer-date-order", value_enum, ignore_case = true)]
+    prefer_date_order: Option<DateOrder>,
+
+    /// Truncate over-long headers and values and report them as warnings
+    #[arg(long = "collect-warnings")]
+    collect_warnings: bool,
+
+    /// Detect column types over the whole file instead of the first lines
+    #[arg(long = "full-scan")]
+    full_scan: bool,
+
+    /// Fail unless every line has the same column count
+    #[arg(long = "strict-columns")]
+    strict_columns: bool,
+
+    /// Report duplicate rows in the sample
+    #[arg(long = "detect-duplicate-rows")]
+    detect_duplicate_rows: bool,
+
+    /// Fail on bytes invalid for the detected charset
+    #[arg(long = "strict-encoding")]
+    strict_encoding: bool,
+
+    /// Fail when the share (0 to 1) of characters that could not be decoded exceeds this ratio
+    #[arg(long = "max-decode-error-ratio", conflicts_with = "strict_encoding")]
+    max_decode_error_ratio: Option<f64>,
+
+    /// Name the email column after the property its header matches, instead of "email"
+    #[arg(long = "email-property-precedence")]
+    email_property_precedence: bool,
+
+    /// Map headers matching no property to the closest one within this edit distance
+    #[arg(long = "fuzzy-match")]
+    fuzzy_match: Option<usize>,
+
+    /// Treat the first row as header regardless of detection
+    #[arg(long = "header", conflicts_with = "no_header")]
+    header: bool,
+
+    /// Treat the first row as data regardless of detection
+    #[arg(long = "no-header")]
+    no_header: bool,
+
+    /// Comma-separated column names of a file without header row
+    #[arg(long = "headers", value_delimiter = ',', conflicts_with = "header")]
+    headers: Option<Vec<String>>,
+
+    /// Remove NUL bytes from the text, reporting them as warnings
+    #[arg(long = "strip-nul-bytes")]
+    strip_nul_bytes: bool,
+
+    /// Print the time spent in each analysis stage to stderr
+    #[arg(long = "timings")]
+    timings: bool,
+
+    /// Detect and skip a units row (e.g. "kg,m") after the header
+    #[arg(long = "detect-units-row")]
+    detect_units_row: bool,
+
+    /// Maximum number of columns
+    #[arg(long = "max-columns")]
+    max_columns: Option<usize>,
+
+    /// Maximum number of fields in any single row
+    #[arg(long = "max-row-fields")]
+    max_row_fields: Option<usize>,
+
+    /// Maximum length in bytes of a column name or field value
+    #[arg(long = "max-field-length")]
+    max_field_length: Option<usize>,
+
+    /// Characters allowed in column names besides letters and digits (e.g. "_-")
+    #[arg(long = "header-chars")]
+    header_chars: Option<String>,
+
+    /// Column count of the file: shorter rows are padded, longer ones truncated
+    #[arg(long = "columns")]
+    columns: Option<usize>,
+
+    /// Drop the empty last column left by a delimiter ending most lines
+    #[arg(long = "trim-trailing-empty-column")]
+    trim_trailing_empty_column: bool,
+
+    /// Remove control characters (e.g. \x1A) ending lines, reporting them as warnings
+    #[arg(long = "strip-trailing-control")]
+    strip_trailing_control: bool,
+
+    /// Unwrap Excel ="0123" text values, keeping their column a String
+    #[arg(long = "unwrap-excel-text")]
+    unwrap_excel_text: bool,
+
+    /// Print the detected profile as JSON, or the parsed sample as CSV
+    #[arg(long = "emit", value_enum)]
+    emit: Option<Emit>,
+
+    /// Only detect the dialect (charset, delimiters, header), without types
+    /// or database
+    #[arg(
+        long = "detect-only",
+        conflicts_with_all = ["emit", "format", "data_base64", "msgpack"]
+    )]
+    detect_only: bool,
+
+    /// Write the success or error response as MessagePack instead of JSON
+    #[arg(long = "msgpack", conflicts_with_all = ["emit", "format", "data_base64"])]
+    msgpack: bool,
+
+    /// Report per-column statistics (min/max, distinct values) of the sample
+    #[arg(long = "stats")]
+    stats: bool,
+
+    /// Report the empty cells per column of the sample
+    #[arg(long = "null-counts")]
+    null_counts: bool,
+
+    /// Estimate the total number of data rows of the file from the sample
+    #[arg(long = "estimate-rows")]
+    estimate_rows: bool,
+
+    /// Report the column likely holding a unique identifier
+    #[arg(long = "detect-id-column")]
+    detect_id_column: bool,
+
+    /// Report the text columns holding http(s) URLs
+    #[arg(long = "detect-url-columns")]
+    detect_url_columns: bool,
+
+    /// Report missing values as null, keeping quoted empty values ("") as empty strings
+    #[arg(long = "null-for-missing")]
+    null_for_missing: bool,
+
+    /// Report a human-readable label of the charset (e.g. "Windows-1252")
+    #[arg(long = "charset-label")]
+    charset_label: bool,
+
+    /// Charset to decode the file with instead of detecting it (e.g. "cp1251")
+    #[arg(long = "charset")]
+    charset: Option<String>,
+
+    /// Trace detection decisions and database interactions to stderr
+    #[arg(long = "debug")]
+    debug: bool,
+
+    /// Don't print non-fatal warnings to stderr
+    #[arg(long = "quiet", conflicts_with_all = ["debug", "timings"])]
+    quiet: bool,
 }
 
 fn main() {
     let args = Args::parse();
+    csvanalyzertool::debug::set_enabled(args.debug);
 
     // Validate file exists
-    if !Path::new(&args.filename).exists() {
-        eprintln!(
-            "{{\"Error\":2,\"ErrorMsgUser\":\"Could not get a sample for analyze. Is file empty?\",\"ErrorMsgInternal\":\"File not found: {}\"}}",
-            args.filename
-        );
-        std::process::exit(1);
+    if let Some(ref filename) = args.filename {
+        if !Path::new(filename).exists() {
+            eprintln!(
+                "{{\"Error\":2,\"ErrorMsgUser\":\"Could not get a sample for analyze. Is file empty?\",\"ErrorMsgInternal\":\"File not found: {}\"}}",
+                filename
+            );
+            std::process::exit(1);
+        }
     }
 
-    // Build database config
+    // Build database config, not needed to detect the dialect
     let db_config = match build_db_config(&args) {
         Ok(config) => config,
+        Err(_) if args.detect_only => DbConfig::default(),
         Err(e) => {
             eprintln!(
                 "{{\"Error\":3,\"ErrorMsgUser\":\"Database configuration error\",\"ErrorMsgInternal\":\"{}\"}}",
@@ -80,22 +322,222 @@ fn main() {
         }
     };
 
-    // Build main config
-    let mut config = Config::new_with_db(args.akid, args.locale, args.filename, db_config);
+    // Build main config, starting from the options file if given
+    let mut config = match args.options_file {
+        Some(ref path) => match load_options(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!(
+                    "{{\"Error\":0,\"ErrorMsgUser\":\"Options file error\",\"ErrorMsgInternal\":\"{}\"}}",
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+    config.akid = args.akid;
+    config.locale = args.locale;
+    config.filename = args.filename.unwrap_or_default();
+    config.db = db_config;
 
+    if let Some(skip_lines) = args.skip_lines {
+        config.skip_lines = skip_lines;
+    }
+    if args.comment_prefix.is_some() {
+        config.comment_prefix = args.comment_prefix;
+    }
     if let Some(scan_lines) = args.scan_lines {
         config.scan_lines = scan_lines;
     }
+    if let Some(sample_bytes) = args.sample_bytes {
+        config.max_bytes = sample_bytes;
+    }
     if let Some(return_lines) = args.return_lines {
         config.return_lines = return_lines;
     }
+    if let Some(return_offset) = args.return_offset {
+        config.return_offset = return_offset;
+    }
+    if let Some(format) = args.format {
+        config.output_format = format;
+    }
+    if args.type_names {
+        config.type_names_as_strings = true;
+    }
+    if args.require_email {
+        config.require_email = true;
+    } else if args.no_require_email {
+        config.require_email = false;
+    }
+    if args.infer_types {
+        config.infer_types = true;
+    } else if args.no_infer_types {
+        config.infer_types = false;
+    }
+    if args.email_regex.is_some() {
+        config.email_regex = args.email_regex;
+    }
+    if args.allowed_tlds.is_some() {
+        config.allowed_tlds = args.allowed_tlds;
+    }
+    if args.escape_char.is_some() {
+        config.escape_char = args.escape_char;
+    }
+    if args.normalize_fullwidth {
+        config.normalize_fullwidth = true;
+    }
+    if args.collect_warnings {
+        config.collect_warnings = true;
+    }
+    if args.full_scan {
+        config.full_scan = true;
+    }
+    if args.strict_columns {
+        config.strict_columns = true;
+    }
+    if args.detect_duplicate_rows {
+        config.detect_duplicate_rows = true;
+    }
+    if args.strict_encoding {
+        config.strict_encoding = true;
+    }
+    if args.max_decode_error_ratio.is_some() {
+        config.max_decode_error_ratio = args.max_decode_error_ratio;
+    }
+    if args.email_property_precedence {
+        config.email_property_precedence = true;
+    }
+    if let Some(distance) = args.fuzzy_match {
+        config.fuzzy_match_distance = Some(distance);
+    }
+    if args.charset_label {
+        config.report_charset_label = true;
+    }
+    if args.charset.is_some() {
+        config.force_charset = args.charset;
+    }
+    if args.strip_nul_bytes {
+        config.strip_nul_bytes = true;
+    }
+    if args.timings {
+        config.profile_timings = true;
+    }
+    if args.detect_units_row {
+        config.detect_units_row = true;
+    }
+    if let Some(max_columns) = args.max_columns {
+        config.max_columns = max_columns;
+    }
+    if let Some(max_row_fields) = args.max_row_fields {
+        config.max_row_fields = max_row_fields;
+    }
+    if let Some(max_field_length) = args.max_field_length {
+        config.max_string_size = max_field_length;
+    }
+    if args.header_chars.is_some() {
+        config.header_chars = args.header_chars;
+    }
+    if args.columns.is_some() {
+        config.expected_columns = args.columns;
+    }
+    if args.trim_trailing_empty_column {
+        config.trim_trailing_empty_column = true;
+    }
+    if args.strip_trailing_control {
+        config.strip_trailing_control = true;
+    }
+    if args.unwrap_excel_text {
+        config.unwrap_excel_text = true;
+    }
+    if let Some(emit) = args.emit {
+        config.emit = emit;
+    }
+    if args.stats {
+        config.compute_stats = true;
+    }
+    if args.null_counts {
+        config.report_null_counts = true;
+    }
+    if args.quiet {
+        config.quiet = true;
+    }
+    if args.estimate_rows {
+        config.estimate_rows = true;
+    }
+    if args.detect_id_column {
+        config.detect_id_column = true;
+    }
+    if args.detect_url_columns {
+        config.detect_url_columns = true;
+    }
+    if args.null_for_missing {
+        config.null_for_missing = true;
+    }
+    if args.header {
+        config.force_header = Some(true);
+    } else if args.no_header {
+        config.force_header = Some(false);
+    }
+    if args.headers.is_some() {
+        config.provided_headers = args.headers;
+    }
+    if args.prefer_date_order.is_some() {
+        config.prefer_date_order = args.prefer_date_order;
+    }
 
     // Run analyzer
     let mut analyzer = CsvAnalyzer::new(config);
-    let result = analyzer.analyze();
+    if args.detect_only {
+        println!("{}", analyzer.detect_only());
+        return;
+    }
+    let output = if args.msgpack {
+        let mut bytes = Vec::new();
+        analyzer.analyze_msgpack(&mut bytes).map(|_| bytes)
+    } else {
+        let mut result = match args.data_base64 {
+            Some(ref data) => analyzer.analyze_base64(data),
+            None => analyzer.analyze(),
+        };
+        // JSON gets a line break, CSV output already ends with one
+        if !result.ends_with('\n') {
+            result.push('\n');
+        }
+        Ok(result.into_bytes())
+    };
+    emit(&analyzer, output);
+}
+
+/// Print the stage timings when requested, then write the analysis output
+/// to stdout, whatever its format
+fn emit(analyzer: &CsvAnalyzer, output: csvanalyzertool::Result<Vec<u8>>) {
+    if let Some(timings) = analyzer.timings() {
+        match serde_json::to_string(timings) {
+            Ok(json) => eprintln!("{}", json),
+            Err(e) => eprintln!("Timings error: {}", e),
+        }
+    }
+    let written = output.and_then(|bytes| {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&bytes)?;
+        Ok(stdout.flush()?)
+    });
+    if let Err(e) = written {
+        eprintln!(
+            "{{\"Error\":0,\"ErrorMsgUser\":\"Output error\",\"ErrorMsgInternal\":\"{}\"}}",
+            e
+        );
+        std::process::exit(1);
+    }
+}
 
-    // Output JSON to stdout
-    println!("{}", result);
+fn load_options(path: &str) -> csvanalyzertool::Result<Config> {
+    if path.ends_with(".json") {
+        Config::from_json(path)
+    } else {
+        Config::from_toml(path)
+    }
 }
 
 fn build_db_config(args: &Args) -> Result<DbConfig, String> {
@@ -125,19 +567,24 @@ fn build_db_config(args: &Args) -> Result<DbConfig, String> {
         db_config = DbConfig::from_env().ok();
     }
 
+    // SQLite needs none of the PostgreSQL settings
+    if db_config.is_none() && args.sqlite_db.is_some() {
+        db_config = Some(DbConfig::default());
+    }
+
     // If still no config, check if we have all required CLI args to build one
     if db_config.is_none() {
-        let has_all_cli_args = args.db_host.is_some()
-            && args.db_name.is_some()
-            && args.db_user.is_some()
-            && args.db_password.is_some();
+        // Without a password, PGPASSFILE or ~/.pgpass is looked up
+        let has_all_cli_args =
+            args.db_host.is_some() && args.db_name.is_some() && args.db_user.is_some();
 
         if !has_all_cli_args {
             return Err(
                 "Database configuration not found. Please provide configuration via:\n\
                  - Config file (--config or /etc/mailjet.conf)\n\
                  - Environment variables (PGHOST, PGPORT, PGDATABASE, PGUSER, PGPASSWORD)\n\
-                 - CLI arguments (--db-host, --db-name, --db-user, --db-password)"
+                 - CLI arguments (--db-host, --db-name, --db-user, --db-password or --db-password-file)\n\
+                 - SQLite file (--sqlite-db)"
                     .to_string(),
             );
         }
@@ -148,7 +595,7 @@ fn build_db_config(args: &Args) -> Result<DbConfig, String> {
             args.db_port.unwrap_or(5432),
             args.db_name.clone().unwrap(),
             args.db_user.clone().unwrap(),
-            args.db_password.clone().unwrap(),
+            args.db_password.clone().unwrap_or_default(),
         ));
     }
 
@@ -171,6 +618,22 @@ fn build_db_config(args: &Args) -> Result<DbConfig, String> {
     if let Some(ref password) = args.db_password {
         config.password = password.clone();
     }
+    if let Some(ref path) = args.db_password_file {
+        config.password.clear();
+        config.password_file = Some(path.into());
+    }
+    if let Some(ref path) = args.sqlite_db {
+        config.sqlite_path = Some(path.into());
+    }
+    if args.contact_meta_table.is_some() {
+        config.contact_meta_table = args.contact_meta_table.clone();
+    }
+    if args.db_connect_timeout.is_some() {
+        config.connect_timeout_secs = args.db_connect_timeout;
+    }
+    if let Some(retries) = args.db_retries {
+        config.connect_retries = retries;
+    }
 
     Ok(config)
 }
@@ -0,0 +1,71 @@
+/// Serialize rows as RFC 4180 CSV: one record per CRLF-terminated line,
+/// fields quoted only when they contain the delimiter, the quote or a line
+/// break, quotes inside quoted fields doubled
+pub fn to_csv(rows: &[Vec<String>], delimiter: char, quote: char) -> String {
+    let mut out = String::new();
+    for row in rows {
+        for (i, field) in row.iter().enumerate() {
+            if i > 0 {
+                out.push(delimiter);
+            }
+            write_field(&mut out, field, delimiter, quote);
+        }
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn write_field(out: &mut String, field: &str, delimiter: char, quote: char) {
+    let needs_quotes = field
+        .chars()
+        .any(|c| c == delimiter || c == quote || c == '\r' || c == '\n');
+    if !needs_quotes {
+        out.push_str(field);
+        return;
+    }
+
+    out.push(quote);
+    for c in field.chars() {
+        if c == quote {
+            out.push(quote);
+        }
+        out.push(c);
+    }
+    out.push(quote);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_to_csv_plain() {
+        let rows = vec![row(&["email", "name"]), row(&["john@example.com", "John"])];
+        assert_eq!(
+            to_csv(&rows, ',', '"'),
+            "email,name\r\njohn@example.com,John\r\n"
+        );
+        assert_eq!(
+            to_csv(&rows, ';', '"'),
+            "email;name\r\njohn@example.com;John\r\n"
+        );
+    }
+
+    #[test]
+    fn test_to_csv_quotes_when_needed() {
+        let rows = vec![row(&["Smith, John", "say \"hi\"", "two\nlines", "plain"])];
+        assert_eq!(
+            to_csv(&rows, ',', '"'),
+            "\"Smith, John\",\"say \"\"hi\"\"\",\"two\nlines\",plain\r\n"
+        );
+        // A comma needs no quotes with another delimiter
+        assert_eq!(
+            to_csv(&[row(&["Smith, John", "x"])], ';', '"'),
+            "Smith, John;x\r\n"
+        );
+    }
+}
@@ -0,0 +1,766 @@
+use crate::types::constants::ERROR_LINE_SIZE;
+use crate::types::{
+    ColumnStats, CsvErrorType, DataType, DataTypeName, DateTimeGranularity, DetectionReport,
+    DuplicateRows, IdSequence, NumberFormat, Warning,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::io::Write;
+
+pub mod csv;
+
+/// How a successful analysis is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Single JSON document, `Data` as arrays of values
+    #[default]
+    Json,
+    /// Single JSON document, `Data` as objects keyed by field/header name
+    JsonObjects,
+    /// Only the returned data rows, one JSON object per line keyed like `JsonObjects`
+    Ndjson,
+    /// Only the pandas dtype of each column, keyed by header name
+    PandasDtypes,
+}
+
+/// What a successful analysis prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Emit {
+    /// The detected profile, in the configured output format
+    #[default]
+    Json,
+    /// The parsed sample re-emitted as RFC 4180 CSV, to check field splitting
+    Csv,
+}
+
+/// Success response JSON structure
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SuccessResponse<'a> {
+    pub skip_header: bool,
+    #[serde(borrow)]
+    pub locale: &'a str,
+    /// Locale the date and number formats point to, a hint for `locale`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_locale: Option<String>,
+    #[serde(borrow)]
+    pub charset: &'a str,
+    /// Human-readable label of the charset (e.g. `Windows-1252`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset_label: Option<String>,
+    pub field_separator: String,
+    /// No field separator was found, each line is a single field
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub single_column: bool,
+    pub text_delimiter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_time_format: Option<String>,
+    pub header_names: Vec<String>,
+    pub field_names: Vec<String>,
+    pub data_types: Vec<DataType>,
+    /// Empty (or missing) cells per column over the sample data rows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null_counts: Option<Vec<usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column_stats: Option<Vec<ColumnStats>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_formats: Option<Vec<Option<NumberFormat>>>,
+    /// Whether each DateTime column holds dates, times or both
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granularities: Option<Vec<Option<DateTimeGranularity>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency_symbols: Option<Vec<Option<String>>>,
+    /// Per-column type names given by the custom type detectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantic_types: Option<Vec<Option<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<Vec<String>>>,
+    /// Cells of `data` that were missing (empty and unquoted), rendered as null
+    #[serde(skip)]
+    pub missing_cells: Option<Vec<Vec<bool>>>,
+    /// Render `DataTypes` in JSON as variant names (e.g. `"Integer"`)
+    /// instead of their codes
+    #[serde(skip)]
+    pub type_names: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_rows: Option<DuplicateRows>,
+    /// Approximate number of data rows in the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_rows: Option<usize>,
+    /// Index (0-based) of the column likely holding a unique identifier
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_column: Option<usize>,
+    /// Start and gaps of the identifier column, when its values increase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_sequence: Option<IdSequence>,
+    /// Indexes (0-based) of the text columns holding URLs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url_columns: Option<Vec<usize>>,
+    /// Units/metadata row found after the header and skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units_row: Option<Vec<String>>,
+    /// Why headers matching a contact property were not mapped to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub match_notes: Vec<String>,
+}
+
+impl<'a> SuccessResponse<'a> {
+    pub fn new(locale: &'a str, charset: &'a str) -> Self {
+        SuccessResponse {
+            skip_header: true,
+            locale,
+            suggested_locale: None,
+            charset,
+            charset_label: None,
+            field_separator: String::new(),
+            single_column: false,
+            text_delimiter: String::new(),
+            date_time_format: None,
+            header_names: Vec::new(),
+            field_names: Vec::new(),
+            data_types: Vec::new(),
+            null_counts: None,
+            column_stats: None,
+            number_formats: None,
+            granularities: None,
+            currency_symbols: None,
+            semantic_types: None,
+            data: None,
+            missing_cells: None,
+            type_names: false,
+            warnings: Vec::new(),
+            duplicate_rows: None,
+            estimated_rows: None,
+            id_column: None,
+            id_sequence: None,
+            url_columns: None,
+            units_row: None,
+            match_notes: Vec::new(),
+        }
+    }
+
+    /// Set per-column number formats, omitted when no column has one
+    pub fn set_number_formats(&mut self, formats: Vec<Option<NumberFormat>>) {
+        self.number_formats = if formats.iter().any(Option::is_some) {
+            Some(formats)
+        } else {
+            None
+        };
+    }
+
+    /// Set per-column datetime granularities, omitted when no column has one
+    pub fn set_granularities(&mut self, granularities: Vec<Option<DateTimeGranularity>>) {
+        self.granularities = if granularities.iter().any(Option::is_some) {
+            Some(granularities)
+        } else {
+            None
+        };
+    }
+
+    /// Set per-column currency symbols, omitted when no column has one
+    pub fn set_currency_symbols(&mut self, symbols: Vec<Option<String>>) {
+        self.currency_symbols = if symbols.iter().any(Option::is_some) {
+            Some(symbols)
+        } else {
+            None
+        };
+    }
+
+    /// Set per-column semantic types, omitted when no detector claimed a column
+    pub fn set_semantic_types(&mut self, types: Vec<Option<String>>) {
+        self.semantic_types = if types.iter().any(Option::is_some) {
+            Some(types)
+        } else {
+            None
+        };
+    }
+
+    /// Set field separator as hex string, or flag a single-column file
+    pub fn set_field_separator(&mut self, sep: char) {
+        if sep != '\0' {
+            self.field_separator = format!("{:02X}", sep as u8);
+        }
+        self.single_column = sep == '\0';
+    }
+
+    /// Set text delimiter as hex string
+    pub fn set_text_delimiter(&mut self, delim: char) {
+        if delim != '\0' {
+            self.text_delimiter = format!("{:02X}", delim as u8);
+        }
+    }
+
+    /// Key of each column in record output: the field name, or the header name if
+    /// unmapped or the field name is taken, with a `~N` suffix if that is taken too
+    pub fn record_keys(&self) -> Vec<String> {
+        let mut used = HashSet::new();
+        // Field names claim their key before any header does
+        let field_keys: Vec<Option<String>> = (0..self.header_names.len())
+            .map(|i| {
+                self.field_names
+                    .get(i)
+                    .filter(|name| !name.is_empty() && used.insert(name.to_string()))
+                    .cloned()
+            })
+            .collect();
+        field_keys
+            .into_iter()
+            .zip(&self.header_names)
+            .map(|(key, header)| {
+                key.unwrap_or_else(|| {
+                    let mut key = header.clone();
+                    let mut n = 2;
+                    while !used.insert(key.clone()) {
+                        key = format!("{}~{}", header, n);
+                        n += 1;
+                    }
+                    key
+                })
+            })
+            .collect()
+    }
+
+    /// Returned data rows as objects keyed by `record_keys`
+    pub fn records(&self) -> Vec<Map<String, Value>> {
+        let keys = self.record_keys();
+        self.data_values()
+            .into_iter()
+            .map(|row| {
+                keys.iter()
+                    .zip(row)
+                    .map(|(key, value)| (key.clone(), value))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returned data rows as JSON values, missing cells as null
+    fn data_values(&self) -> Vec<Vec<Value>> {
+        let no_missing = Vec::new();
+        self.data
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, row)| {
+                let missing = self
+                    .missing_cells
+                    .as_ref()
+                    .and_then(|cells| cells.get(i))
+                    .unwrap_or(&no_missing);
+                row.iter()
+                    .enumerate()
+                    .map(|(j, value)| match missing.get(j) {
+                        Some(true) => Value::Null,
+                        _ => Value::String(value.clone()),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Render in the requested output format
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Json => self.to_json(),
+            OutputFormat::JsonObjects => self.to_json_objects(),
+            OutputFormat::Ndjson => to_ndjson(self),
+            OutputFormat::PandasDtypes => to_pandas_dtypes(self),
+        }
+    }
+
+    /// Convert to JSON string
+    pub fn to_json(&self) -> String {
+        let null_cells = self.missing_cells.is_some() && self.data.is_some();
+        if !null_cells && !self.type_names {
+            return serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+        }
+        let mut value = match serde_json::to_value(self) {
+            Ok(value) => value,
+            Err(_) => return "{}".to_string(),
+        };
+        if null_cells {
+            let rows = self.data_values().into_iter().map(Value::Array).collect();
+            value["Data"] = Value::Array(rows);
+        }
+        self.name_types(&mut value);
+        value.to_string()
+    }
+
+    /// Convert to JSON string with `Data` as an array of objects
+    pub fn to_json_objects(&self) -> String {
+        let mut value = match serde_json::to_value(self) {
+            Ok(value) => value,
+            Err(_) => return "{}".to_string(),
+        };
+        if self.data.is_some() {
+            let records = self.records().into_iter().map(Value::Object).collect();
+            value["Data"] = Value::Array(records);
+        }
+        self.name_types(&mut value);
+        value.to_string()
+    }
+
+    /// Replace the `DataTypes` codes of the serialized response by their
+    /// names, when `type_names` is set
+    fn name_types(&self, value: &mut Value) {
+        if self.type_names {
+            let names: Vec<DataTypeName> =
+                self.data_types.iter().copied().map(DataTypeName).collect();
+            value["DataTypes"] = serde_json::to_value(names).unwrap_or_default();
+        }
+    }
+
+    /// Write as MessagePack, a map keyed like the JSON output. Missing cells
+    /// are empty strings, `null_for_missing` only applies to JSON.
+    pub fn write_msgpack<W: Write>(&self, writer: &mut W) -> Result<(), rmp_serde::encode::Error> {
+        rmp_serde::encode::write_named(writer, self)
+    }
+}
+
+/// Error response JSON structure
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ErrorResponse<'a> {
+    pub error: u8,
+    pub error_msg_user: &'static str,
+    pub error_msg_internal: String,
+    pub error_row: usize,
+    pub error_column: usize,
+    pub error_field: String,
+    pub error_data_type: u8,
+    pub error_column_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_line_number: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_line: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_context: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_byte_offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_value_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_char: Option<String>,
+    pub skip_header: bool,
+    #[serde(borrow)]
+    pub locale: &'a str,
+    #[serde(borrow)]
+    pub charset: &'a str,
+    pub field_separator: String,
+    pub text_delimiter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_names: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_names: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_types: Option<Vec<DataType>>,
+}
+
+impl<'a> ErrorResponse<'a> {
+    pub fn new(error_type: CsvErrorType, locale: &'a str, charset: &'a str) -> Self {
+        ErrorResponse {
+            error: error_type as u8,
+            error_msg_user: error_type.message(),
+            error_msg_internal: String::new(),
+            error_row: 0,
+            error_column: 0,
+            error_field: String::new(),
+            error_data_type: 0,
+            error_column_count: 0,
+            error_line_number: None,
+            error_line: None,
+            error_context: None,
+            error_byte_offset: None,
+            error_value_length: None,
+            error_char: None,
+            skip_header: false,
+            locale,
+            charset,
+            field_separator: String::new(),
+            text_delimiter: String::new(),
+            header_names: None,
+            field_names: None,
+            data_types: None,
+        }
+    }
+
+    /// Set internal error message
+    pub fn with_internal_message(mut self, msg: impl Into<String>) -> Self {
+        self.error_msg_internal = msg.into();
+        self
+    }
+
+    /// Set error location
+    pub fn with_location(mut self, row: usize, col: usize) -> Self {
+        self.error_row = row;
+        self.error_column = col;
+        self
+    }
+
+    /// Set error field
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.error_field = field.into();
+        self
+    }
+
+    /// Set error data type
+    pub fn with_data_type(mut self, dt: DataType) -> Self {
+        self.error_data_type = dt as u8;
+        self
+    }
+
+    /// Set error column count
+    pub fn with_column_count(mut self, count: usize) -> Self {
+        self.error_column_count = count;
+        self
+    }
+
+    /// Set the offending source line (1-based) and its text, truncated
+    pub fn with_line(mut self, line_number: usize, line: &str) -> Self {
+        self.error_line_number = Some(line_number);
+        self.error_line = Some(truncate_line(line));
+        self
+    }
+
+    /// Set the source lines around the offending one, truncated
+    pub fn with_context(mut self, lines: &[&str]) -> Self {
+        self.error_context = Some(lines.iter().map(|l| truncate_line(l)).collect());
+        self
+    }
+
+    /// Set the byte offset in the file where the offending data was found
+    pub fn with_byte_offset(mut self, offset: usize) -> Self {
+        self.error_byte_offset = Some(offset);
+        self
+    }
+
+    /// Set the length in bytes of an over-long value, as checked against the limit
+    pub fn with_value_length(mut self, length: usize) -> Self {
+        self.error_value_length = Some(length);
+        self
+    }
+
+    /// Set the character a header name is not allowed to contain
+    pub fn with_char(mut self, c: char) -> Self {
+        self.error_char = Some(c.to_string());
+        self
+    }
+
+    /// Set field separator as hex string
+    pub fn with_field_separator(mut self, sep: char) -> Self {
+        if sep != '\0' {
+            self.field_separator = format!("{:02X}", sep as u8);
+        }
+        self
+    }
+
+    /// Set text delimiter as hex string
+    pub fn with_text_delimiter(mut self, delim: char) -> Self {
+        if delim != '\0' {
+            self.text_delimiter = format!("{:02X}", delim as u8);
+        }
+        self
+    }
+
+    /// Set header names
+    pub fn with_headers(mut self, headers: Vec<String>) -> Self {
+        self.header_names = Some(headers);
+        self
+    }
+
+    /// Convert to JSON string
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Write as MessagePack, a map keyed like the JSON output
+    pub fn write_msgpack<W: Write>(&self, writer: &mut W) -> Result<(), rmp_serde::encode::Error> {
+        rmp_serde::encode::write_named(writer, self)
+    }
+}
+
+/// Dialect of a file found by the detection stages alone, keyed and
+/// hex-encoded like the success response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DialectResponse<'a> {
+    pub charset: &'a str,
+    pub field_separator: String,
+    pub text_delimiter: String,
+    pub skip_header: bool,
+}
+
+impl<'a> DialectResponse<'a> {
+    pub fn new(report: &'a DetectionReport) -> Self {
+        let hex = |c: char| {
+            if c == '\0' {
+                String::new()
+            } else {
+                format!("{:02X}", c as u8)
+            }
+        };
+        DialectResponse {
+            charset: &report.charset,
+            field_separator: hex(report.field_delimiter),
+            text_delimiter: hex(report.text_delimiter),
+            skip_header: report.has_header,
+        }
+    }
+
+    /// Convert to JSON string
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// Returned data rows as newline-delimited JSON, one object per line keyed
+/// by `record_keys`
+pub fn to_ndjson(response: &SuccessResponse) -> String {
+    response
+        .records()
+        .into_iter()
+        .map(|record| Value::Object(record).to_string() + "\n")
+        .collect()
+}
+
+/// JSON object mapping each header name to the pandas dtype of its column,
+/// ready for `pandas.read_csv(dtype=...)`
+pub fn to_pandas_dtypes(response: &SuccessResponse) -> String {
+    let dtypes: Map<String, Value> = response
+        .header_names
+        .iter()
+        .zip(&response.data_types)
+        .map(|(header, data_type)| (header.clone(), Value::from(data_type.pandas_dtype())))
+        .collect();
+    Value::Object(dtypes).to_string()
+}
+
+/// Keep the first ERROR_LINE_SIZE characters of a source line
+fn truncate_line(line: &str) -> String {
+    line.chars().take(ERROR_LINE_SIZE).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success_response_json() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.set_field_separator(',');
+        response.set_text_delimiter('"');
+        response.header_names = vec!["email".to_string(), "name".to_string()];
+        response.field_names = vec!["email".to_string(), "firstname".to_string()];
+        response.data_types = vec![DataType::String, DataType::String];
+
+        let json = response.to_json();
+        assert!(json.contains("\"FieldSeparator\":\"2C\""));
+        assert!(json.contains("\"TextDelimiter\":\"22\""));
+        assert!(json.contains("\"HeaderNames\""));
+    }
+
+    #[test]
+    fn test_error_response_json() {
+        let response = ErrorResponse::new(CsvErrorType::EmailNotFound, "en_US", "utf8");
+
+        let json = response.to_json();
+        assert!(json.contains("\"Error\":9"));
+        assert!(json.contains("\"ErrorMsgUser\":\"Email column not found\""));
+    }
+
+    #[test]
+    fn test_success_response_json_objects() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.header_names = vec!["Mail".to_string(), "Nick".to_string()];
+        response.field_names = vec!["email".to_string(), String::new()];
+        response.data_types = vec![DataType::String, DataType::String];
+        response.data = Some(vec![
+            vec!["john@example.com".to_string(), "Johnny".to_string()],
+            vec!["jane@example.com".to_string(), "Janie".to_string()],
+        ]);
+
+        let json: Value =
+            serde_json::from_str(&response.render(OutputFormat::JsonObjects)).unwrap();
+        assert_eq!(
+            json["Data"],
+            serde_json::json!([
+                {"email": "john@example.com", "Nick": "Johnny"},
+                {"email": "jane@example.com", "Nick": "Janie"},
+            ])
+        );
+        assert_eq!(json["HeaderNames"], serde_json::json!(["Mail", "Nick"]));
+    }
+
+    #[test]
+    fn test_record_keys_unique() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.header_names = ["email", "mobile", "phone", "phone"]
+            .map(String::from)
+            .to_vec();
+        response.field_names = ["email", "phone", "", ""].map(String::from).to_vec();
+        assert_eq!(
+            response.record_keys(),
+            ["email", "phone", "phone~2", "phone~3"]
+        );
+
+        // Repeated field names fall back to the header
+        response.field_names = ["email", "phone", "phone", ""].map(String::from).to_vec();
+        assert_eq!(
+            response.record_keys(),
+            ["email", "phone", "phone~2", "phone~3"]
+        );
+        response.header_names[2] = "home".to_string();
+        assert_eq!(
+            response.record_keys(),
+            ["email", "phone", "home", "phone~2"]
+        );
+
+        response.data = Some(vec![["a@example.com", "1", "2", "3"]
+            .map(String::from)
+            .to_vec()]);
+        assert_eq!(response.records()[0].len(), 4);
+    }
+
+    #[test]
+    fn test_type_names() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.header_names = vec!["email".to_string(), "when".to_string()];
+        response.field_names = vec!["email".to_string(), String::new()];
+        response.data_types = vec![DataType::String, DataType::DateTime];
+        response.data = Some(vec![vec![
+            "john@example.com".to_string(),
+            "2024-01-31".to_string(),
+        ]]);
+
+        let json: Value = serde_json::from_str(&response.to_json()).unwrap();
+        assert_eq!(json["DataTypes"], serde_json::json!([0, 4]));
+
+        response.type_names = true;
+        for format in [OutputFormat::Json, OutputFormat::JsonObjects] {
+            let json: Value = serde_json::from_str(&response.render(format)).unwrap();
+            assert_eq!(json["DataTypes"], serde_json::json!(["String", "DateTime"]));
+        }
+
+        // Missing cells are still rendered as null
+        response.missing_cells = Some(vec![vec![false, true]]);
+        let json: Value = serde_json::from_str(&response.to_json()).unwrap();
+        assert_eq!(json["Data"][0][1], Value::Null);
+        assert_eq!(json["DataTypes"][1], "DateTime");
+    }
+
+    #[test]
+    fn test_dialect_response() {
+        let report = DetectionReport {
+            charset: "utf8".to_string(),
+            text_delimiter: '"',
+            field_delimiter: '\t',
+            has_header: true,
+            column_count: 3,
+        };
+        assert_eq!(
+            DialectResponse::new(&report).to_json(),
+            r#"{"Charset":"utf8","FieldSeparator":"09","TextDelimiter":"22","SkipHeader":true}"#
+        );
+
+        let report = DetectionReport {
+            field_delimiter: '\0',
+            has_header: false,
+            ..report
+        };
+        let json: Value = serde_json::from_str(&DialectResponse::new(&report).to_json()).unwrap();
+        assert_eq!(json["FieldSeparator"], "");
+        assert_eq!(json["SkipHeader"], false);
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.header_names = vec!["Mail".to_string(), "Nick".to_string()];
+        response.field_names = vec!["email".to_string(), String::new()];
+        response.data_types = vec![DataType::String, DataType::String];
+        assert_eq!(to_ndjson(&response), "");
+
+        response.data = Some(vec![
+            vec!["john@example.com".to_string(), "Johnny".to_string()],
+            vec!["jane@example.com".to_string(), "Janie".to_string()],
+            vec!["bob@example.com".to_string(), "Bobby".to_string()],
+        ]);
+        let ndjson = response.render(OutputFormat::Ndjson);
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            serde_json::from_str::<Value>(lines[1]).unwrap(),
+            serde_json::json!({"email": "jane@example.com", "Nick": "Janie"})
+        );
+    }
+
+    #[test]
+    fn test_pandas_dtypes() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.header_names = vec!["Mail".to_string(), "Age".to_string()];
+        response.field_names = vec!["email".to_string(), String::new()];
+        response.data_types = vec![DataType::String, DataType::Integer];
+
+        let json: Value =
+            serde_json::from_str(&response.render(OutputFormat::PandasDtypes)).unwrap();
+        assert_eq!(json, serde_json::json!({"Mail": "object", "Age": "Int64"}));
+    }
+
+    #[test]
+    fn test_success_response_msgpack() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.skip_header = true;
+        response.set_field_separator(';');
+        response.header_names = vec!["Mail".to_string(), "Age".to_string()];
+        response.field_names = vec!["email".to_string(), String::new()];
+        response.data_types = vec![DataType::String, DataType::Integer];
+        response.null_counts = Some(vec![0, 1]);
+        response.data = Some(vec![vec!["john@example.com".to_string(), String::new()]]);
+
+        let mut bytes = Vec::new();
+        response.write_msgpack(&mut bytes).unwrap();
+        let decoded: SuccessResponse = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn test_error_response_msgpack() {
+        let response =
+            ErrorResponse::new(CsvErrorType::EmailNotFound, "en_US", "utf8").with_location(3, 2);
+
+        let mut bytes = Vec::new();
+        response.write_msgpack(&mut bytes).unwrap();
+        let decoded: Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(
+            decoded,
+            serde_json::from_str::<Value>(&response.to_json()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hex_encoding() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        response.set_field_separator(';');
+        assert_eq!(response.field_separator, "3B");
+
+        response.set_field_separator('\t');
+        assert_eq!(response.field_separator, "09");
+        assert!(!response.single_column);
+    }
+
+    #[test]
+    fn test_single_column_flag() {
+        let mut response = SuccessResponse::new("en_US", "utf8");
+        assert!(!response.to_json().contains("SingleColumn"));
+
+        response.set_field_separator('\0');
+        let json: Value = serde_json::from_str(&response.to_json()).unwrap();
+        assert_eq!(json["FieldSeparator"], "");
+        assert_eq!(json["SingleColumn"], true);
+    }
+}
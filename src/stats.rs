@@ -0,0 +1,213 @@
+use crate::detection::currency::parse_currency;
+use crate::detection::percent::strip_percent;
+use crate::types::constants::MAX_DISTINCT_VALUES;
+use crate::types::{ColumnStats, DataType, IdSequence};
+use std::collections::HashSet;
+
+/// Compute the statistics of a column's sampled values.
+/// Min and max are only computed for Integer and Float columns.
+pub fn column_stats(values: &[&str], data_type: DataType) -> ColumnStats {
+    let mut distinct: HashSet<&str> = HashSet::new();
+    for value in values.iter().map(|v| v.trim()) {
+        if distinct.len() >= MAX_DISTINCT_VALUES {
+            break;
+        }
+        if !value.is_empty() {
+            distinct.insert(value);
+        }
+    }
+
+    let mut stats = ColumnStats {
+        min: None,
+        max: None,
+        distinct: distinct.len(),
+    };
+    if matches!(data_type, DataType::Integer | DataType::Float) {
+        for number in values.iter().filter_map(|v| parse_number(v)) {
+            stats.min = Some(stats.min.map_or(number, |min: f64| min.min(number)));
+            stats.max = Some(stats.max.map_or(number, |max: f64| max.max(number)));
+        }
+    }
+    stats
+}
+
+/// Detect a likely unique identifier column: every value non-empty and
+/// distinct within the sample. Integer columns are preferred, then the
+/// leftmost one. Needs at least two rows to tell anything apart.
+pub fn detect_id_column(rows: &[Vec<String>], data_types: &[DataType]) -> Option<usize> {
+    if rows.len() < 2 {
+        return None;
+    }
+
+    let candidates: Vec<usize> = (0..data_types.len())
+        .filter(|&col| {
+            let mut seen = HashSet::new();
+            rows.iter().all(|row| match row.get(col).map(|v| v.trim()) {
+                Some(value) if !value.is_empty() => seen.insert(value),
+                _ => false,
+            })
+        })
+        .collect();
+
+    candidates
+        .iter()
+        .find(|&&col| data_types[col] == DataType::Integer)
+        .or_else(|| candidates.first())
+        .copied()
+}
+
+/// Sequence of a column of strictly increasing integers, telling where it
+/// starts and whether values are skipped. None for any other column.
+pub fn id_sequence(values: &[&str]) -> Option<IdSequence> {
+    let numbers: Vec<i64> = values
+        .iter()
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let start = *numbers.first()?;
+    let mut gaps = false;
+    for pair in numbers.windows(2) {
+        if pair[1] <= pair[0] {
+            return None;
+        }
+        gaps |= pair[1] - pair[0] > 1;
+    }
+    Some(IdSequence { start, gaps })
+}
+
+/// Estimate the number of lines of a file from a sample of its first bytes,
+/// scaling the complete lines of the sample to the file size. Exact when the
+/// sample holds the whole file.
+pub fn estimate_line_count(sample: &[u8], file_size: u64) -> usize {
+    let complete = match memchr::memrchr(b'\n', sample) {
+        Some(pos) => pos + 1,
+        None => return usize::from(!sample.is_empty()),
+    };
+    let lines = memchr::memchr_iter(b'\n', &sample[..complete]).count();
+
+    if sample.len() as u64 >= file_size {
+        // A last line without line break counts too
+        return lines + usize::from(complete < sample.len());
+    }
+    (file_size as f64 * lines as f64 / complete as f64).round() as usize
+}
+
+/// Numeric value of a plain, percent or currency value, with `.` or `,` as
+/// decimal separator
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if let Some((_, amount)) = parse_currency(value) {
+        return Some(amount);
+    }
+    let number = strip_percent(value).unwrap_or(value);
+    number.replace(',', ".").parse::<f64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_column_stats() {
+        let values = vec!["12", "-3", "", "40", "12"];
+        let stats = column_stats(&values, DataType::Integer);
+        assert_eq!(stats.min, Some(-3.0));
+        assert_eq!(stats.max, Some(40.0));
+        assert_eq!(stats.distinct, 3);
+
+        let values = vec!["1,5", "12.5%", "$1,234.56"];
+        let stats = column_stats(&values, DataType::Float);
+        assert_eq!(stats.min, Some(1.5));
+        assert_eq!(stats.max, Some(1234.56));
+    }
+
+    #[test]
+    fn test_string_column_stats() {
+        let values = vec!["a", "b", "a"];
+        let stats = column_stats(&values, DataType::String);
+        assert_eq!(stats.min, None);
+        assert_eq!(stats.max, None);
+        assert_eq!(stats.distinct, 2);
+    }
+
+    #[test]
+    fn test_detect_id_column() {
+        let rows: Vec<Vec<String>> = [
+            ["a@example.com", "10", "7", "Ann"],
+            ["b@example.com", "11", "7", "Bob"],
+            ["c@example.com", "12", "8", "Cid"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|v| v.to_string()).collect())
+        .collect();
+        let types = [
+            DataType::String,
+            DataType::Integer,
+            DataType::Integer,
+            DataType::String,
+        ];
+
+        // The leftmost unique integer column wins over earlier string ones
+        assert_eq!(detect_id_column(&rows, &types), Some(1));
+        // Without integers, the leftmost unique column
+        assert_eq!(detect_id_column(&rows, &[DataType::String; 4]), Some(0));
+        assert_eq!(detect_id_column(&rows[..1], &types), None);
+    }
+
+    #[test]
+    fn test_no_id_column() {
+        let rows: Vec<Vec<String>> = vec![
+            vec!["1".to_string(), "x".to_string()],
+            vec!["".to_string(), "x".to_string()],
+        ];
+        assert_eq!(
+            detect_id_column(&rows, &[DataType::Integer, DataType::String]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_id_sequence() {
+        assert_eq!(
+            id_sequence(&["0", "1", "2", "3"]),
+            Some(IdSequence {
+                start: 0,
+                gaps: false
+            })
+        );
+        assert_eq!(
+            id_sequence(&["1", "3", "5"]),
+            Some(IdSequence {
+                start: 1,
+                gaps: true
+            })
+        );
+        // Not increasing, or not integers
+        assert_eq!(id_sequence(&["2", "1", "3"]), None);
+        assert_eq!(id_sequence(&["1", "1"]), None);
+        assert_eq!(id_sequence(&["1", "a"]), None);
+        assert_eq!(id_sequence(&[]), None);
+    }
+
+    #[test]
+    fn test_estimate_line_count() {
+        let whole = b"a,b\n1,2\n3,4";
+        assert_eq!(estimate_line_count(whole, whole.len() as u64), 3);
+        assert_eq!(estimate_line_count(b"", 0), 0);
+
+        // 4 lines of 8 bytes sampled out of 800 bytes
+        let sample = b"1234567\n1234567\n1234567\n1234567\n1234";
+        assert_eq!(estimate_line_count(sample, 800), 100);
+    }
+
+    #[test]
+    fn test_distinct_count_capped() {
+        let owned: Vec<String> = (0..MAX_DISTINCT_VALUES + 50)
+            .map(|i| i.to_string())
+            .collect();
+        let values: Vec<&str> = owned.iter().map(String::as_str).collect();
+        assert_eq!(
+            column_stats(&values, DataType::Integer).distinct,
+            MAX_DISTINCT_VALUES
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::fmt;
+use std::time::Duration;
 
 /// Data type codes matching Pascal mjconsts.pas
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
@@ -14,6 +16,87 @@ pub enum DataType {
     DateTime = 4,
 }
 
+impl DataType {
+    /// pandas dtype holding values of this type, as passed to `read_csv(dtype=...)`.
+    /// Integer and Boolean use the nullable extension types, `int64` and
+    /// `bool` fail on empty values.
+    pub fn pandas_dtype(&self) -> &'static str {
+        match self {
+            DataType::String => "object",
+            DataType::Integer => "Int64",
+            DataType::Float => "float64",
+            DataType::Boolean => "boolean",
+            DataType::DateTime => "datetime64[ns]",
+        }
+    }
+
+    /// Variant name, e.g. `DateTime`
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataType::String => "String",
+            DataType::Integer => "Integer",
+            DataType::Float => "Float",
+            DataType::Boolean => "Boolean",
+            DataType::DateTime => "DateTime",
+        }
+    }
+}
+
+/// DataType serializing as its variant name instead of its Pascal code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataTypeName(pub DataType);
+
+impl Serialize for DataTypeName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.name())
+    }
+}
+
+/// Number format of a numeric column, reported alongside its DataType
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberFormat {
+    /// Values carry a trailing '%' (e.g. "12.5%")
+    Percent,
+    /// Values carry a currency symbol (e.g. "$1,234.56")
+    Currency,
+}
+
+/// What the values of a DateTime column carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateTimeGranularity {
+    /// e.g. "2020-01-15"
+    Date,
+    /// e.g. "2020-01-15 10:30"
+    DateTime,
+    /// e.g. "10:30"
+    Time,
+}
+
+/// Day/month/year order preferred when date values fit several patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DateOrder {
+    /// e.g. "dd/mm/yyyy"
+    Dmy,
+    /// e.g. "mm/dd/yyyy"
+    Mdy,
+    /// e.g. "yyyy-mm-dd"
+    Ymd,
+}
+
+impl DateOrder {
+    /// Order string as used by `DatePattern::order`
+    pub fn as_pattern_order(&self) -> &'static str {
+        match self {
+            DateOrder::Dmy => "d/m/y",
+            DateOrder::Mdy => "m/d/y",
+            DateOrder::Ymd => "y/m/d",
+        }
+    }
+}
+
 /// CSV error type codes matching Pascal csvanalyzer.pas
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
@@ -28,6 +111,9 @@ pub enum CsvErrorType {
     ValueLong = 7,          // Field value too long
     DuplicateField = 8,     // Duplicate column name in header
     EmailNotFound = 9,      // No email column detected
+    Encoding = 10,          // Content invalid for its charset
+    TypeMismatch = 11,      // Column type differs from the expected one
+    HeaderChar = 12,        // Column name with a character not allowed
 }
 
 impl fmt::Display for CsvErrorType {
@@ -51,6 +137,11 @@ impl CsvErrorType {
             CsvErrorType::ValueLong => "Value \"%s\" in row %d, column %d is too long",
             CsvErrorType::DuplicateField => "Duplicate field name \"%s\"",
             CsvErrorType::EmailNotFound => "Email column not found",
+            CsvErrorType::Encoding => "File content does not match its character encoding",
+            CsvErrorType::TypeMismatch => "Column %d does not have the expected data type",
+            CsvErrorType::HeaderChar => {
+                "Column name \"%s\" in column %d contains a character that is not allowed"
+            }
         }
     }
 }
@@ -62,6 +153,140 @@ pub struct ContactProperty {
     pub datatype: DataType,
 }
 
+/// Kind of issue reported as a warning instead of failing the analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WarningKind {
+    /// Column name too long, truncated
+    ColumnLong,
+    /// Field value too long, truncated
+    ValueLong,
+    /// NUL bytes removed from the line
+    NulBytes,
+    /// Column type differs from the expected one
+    TypeMismatch,
+    /// Row has more columns than expected, truncated
+    ExtraColumns,
+    /// Control characters (e.g. `\x1A`) removed from the end of the line
+    ControlChars,
+    /// Line ending (`\r\n`, `\n` or `\r`) other than the first line's
+    MixedLineEndings,
+}
+
+/// Issue found at a row/column (1-based, like the error location),
+/// column 0 when it concerns the whole line, row 0 the whole column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Warning {
+    pub row: usize,
+    pub col: usize,
+    pub kind: WarningKind,
+}
+
+/// Rows of the sample repeating an earlier row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DuplicateRows {
+    /// Number of rows identical to an earlier one
+    pub count: usize,
+    /// Line number (1-based) of the first such row
+    pub first_row: usize,
+}
+
+/// Increasing integer sequence of an identifier column over the sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct IdSequence {
+    /// First value, 0 or 1 for a zero-based or one-based index
+    pub start: i64,
+    /// Whether some values are skipped (e.g. `1,3,5`)
+    pub gaps: bool,
+}
+
+/// Simple statistics of a column over the sample data rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ColumnStats {
+    /// Smallest value of an Integer/Float column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Largest value of an Integer/Float column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Distinct non-empty values, counted up to MAX_DISTINCT_VALUES
+    pub distinct: usize,
+}
+
+/// Format of a CSV sample found by the detection stages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectionReport {
+    /// Normalized charset name (e.g. `utf8`, `cp1252`)
+    pub charset: String,
+    /// Quote character, `"` when no value is quoted
+    pub text_delimiter: char,
+    /// Field delimiter, `'\0'` for a single column
+    pub field_delimiter: char,
+    pub has_header: bool,
+    pub column_count: usize,
+}
+
+/// Time spent in each stage of the last analysis,
+/// None for stages that did not run (e.g. replayed from a profile).
+/// Serialized in milliseconds, to the microsecond.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StageTimings {
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_read: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub charset: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub quote: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub delimiter: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub header: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub column_validation: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub type_detection: Option<Duration>,
+    #[serde(
+        serialize_with = "serialize_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub db: Option<Duration>,
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_f64(duration.as_micros() as f64 / 1000.0),
+        None => serializer.serialize_none(),
+    }
+}
+
 /// Date pattern for detection
 #[derive(Debug, Clone)]
 pub struct DatePattern {
@@ -81,15 +306,32 @@ pub struct TimePattern {
 pub mod constants {
     pub const MAX_SCAN_LINES: usize = 1000;
     pub const MAX_RETURN_LINES: usize = 10;
+    /// Distinct values counted per column in statistics
+    pub const MAX_DISTINCT_VALUES: usize = 1000;
     pub const MAX_COLUMNS: usize = 200;
+    /// Fields of any single row, beyond which parsing stops with an error
+    pub const MAX_ROW_FIELDS: usize = 10_000;
     pub const MAX_STRING_SIZE: usize = 1000;
+    /// Characters of a source line kept in error responses
+    pub const ERROR_LINE_SIZE: usize = 200;
+    /// Lines shown before and after the offending line in error responses
+    pub const ERROR_CONTEXT_LINES: usize = 1;
     pub const BUFF_SIZE: usize = 10240; // 10KB
     pub const MAX_BYTES: usize = 51200; // 50KB
     pub const FIELD_DELIM_PERCENT: usize = 50;
     pub const TEXT_SEP_PERCENT: usize = 50;
     pub const COLUMN_COUNT_PERCENT: usize = 90;
+    /// Share of lines (in percent) that must end with an empty field for it
+    /// to be dropped as a trailing delimiter
+    pub const TRAILING_EMPTY_PERCENT: usize = 90;
+    /// Share of non-empty values (in percent) that must look like phone numbers
+    pub const PHONE_COLUMN_PERCENT: usize = 80;
+    /// Digits in a phone number, E.164 allows up to 15
+    pub const PHONE_MIN_DIGITS: usize = 7;
+    pub const PHONE_MAX_DIGITS: usize = 15;
+    /// Share of non-empty values (in percent) that must look like URLs
+    pub const URL_COLUMN_PERCENT: usize = 80;
     pub const MAX_BUCKET: usize = 4;
-    pub const CSVA_GUESS_SIZE: usize = 5120; // 5KB threshold for quick charset guess
 
     /// Candidate field delimiters in priority order
     pub const FIELD_DELIMS: [char; 6] = ['\x0B', ',', ';', '|', ' ', '\t'];
@@ -156,6 +398,37 @@ pub const DATE_PATTERNS: &[DatePattern] = &[
         separator: '-',
         order: "m/d/y",
     },
+    // Two-digit years, after the four-digit patterns so these are preferred
+    DatePattern {
+        pattern: "dd-mm-yy",
+        separator: '-',
+        order: "d/m/y",
+    },
+    DatePattern {
+        pattern: "dd/mm/yy",
+        separator: '/',
+        order: "d/m/y",
+    },
+    DatePattern {
+        pattern: "dd.mm.yy",
+        separator: '.',
+        order: "d/m/y",
+    },
+    DatePattern {
+        pattern: "mm/dd/yy",
+        separator: '/',
+        order: "m/d/y",
+    },
+    DatePattern {
+        pattern: "mm.dd.yy",
+        separator: '.',
+        order: "m/d/y",
+    },
+    DatePattern {
+        pattern: "mm-dd-yy",
+        separator: '-',
+        order: "m/d/y",
+    },
 ];
 
 /// Time patterns matching Pascal mjutils.pas TIME_PATTERNS
@@ -1,22 +1,53 @@
 use crate::detection::delimiter::count_delimiters;
 use crate::error::{CsvAnalyzerError, Result};
-use crate::types::constants::{COLUMN_COUNT_PERCENT, MAX_BUCKET, MAX_COLUMNS, MAX_STRING_SIZE};
+use crate::types::constants::{MAX_BUCKET, TRAILING_EMPTY_PERCENT};
 use crate::types::CsvErrorType;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Validation results
 #[derive(Debug, Clone)]
 pub struct ValidationResult {
     pub columns_count: usize,
+    /// First line (1-based) whose column count differs from the dominant one, 0 if none
     pub error_row: usize,
+    /// Number of lines having each column count, by increasing column count
+    pub bucket: Vec<(usize, usize)>,
+}
+
+/// Column count distribution for error messages, e.g.
+/// `3 cols: 8 rows, 4 cols: 2 rows`
+pub fn describe_bucket(bucket: &[(usize, usize)]) -> String {
+    bucket
+        .iter()
+        .map(|(columns, lines)| format!("{} cols: {} rows", columns, lines))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// VariousFieldsCount error giving the column count distribution
+fn various_fields_count(bucket: &HashMap<usize, usize>) -> CsvAnalyzerError {
+    CsvAnalyzerError::CsvErrorDetail(
+        CsvErrorType::VariousFieldsCount,
+        describe_bucket(&sorted_bucket(bucket)),
+    )
+}
+
+fn sorted_bucket(bucket: &HashMap<usize, usize>) -> Vec<(usize, usize)> {
+    let mut sorted: Vec<(usize, usize)> = bucket.iter().map(|(&c, &n)| (c, n)).collect();
+    sorted.sort_unstable();
+    sorted
 }
 
 /// Validate that column counts are consistent across lines.
-/// Returns the dominant column count if valid.
+/// Returns the dominant column count if valid: shared by at least
+/// `column_count_percent` of the lines and at most `max_columns`.
 pub fn validate_columns_count(
     lines: &[&str],
     delimiter: char,
     text_sep: char,
+    escape_char: Option<char>,
+    max_columns: usize,
+    column_count_percent: usize,
 ) -> Result<ValidationResult> {
     if lines.is_empty() {
         return Err(CsvAnalyzerError::CsvError(CsvErrorType::Sample));
@@ -27,61 +58,84 @@ pub fn validate_columns_count(
         return Ok(ValidationResult {
             columns_count: 1,
             error_row: 0,
+            bucket: vec![(1, lines.len())],
         });
     }
 
     // Count columns in each line (bucket approach like Pascal)
     let mut bucket: HashMap<usize, usize> = HashMap::new();
+    let mut line_counts: Vec<usize> = Vec::with_capacity(lines.len());
 
     for line in lines.iter() {
         // +1 because N delimiters = N+1 columns
-        let col_count = count_delimiters(delimiter, line, text_sep) + 1;
+        let col_count = count_delimiters(delimiter, line, text_sep, escape_char) + 1;
+        line_counts.push(col_count);
 
         let entry = bucket.entry(col_count).or_insert(0);
         *entry += 1;
 
         // Too many different column counts
         if bucket.len() > MAX_BUCKET {
-            return Err(CsvAnalyzerError::CsvError(CsvErrorType::VariousFieldsCount));
+            return Err(various_fields_count(&bucket));
         }
     }
 
-    // Find the dominant column count (must be >= COLUMN_COUNT_PERCENT)
+    // Find the dominant column count (must be >= column_count_percent)
     let total_lines = lines.len();
     for (&col_count, &occurrence) in bucket.iter() {
         let percentage = (occurrence * 100) / total_lines;
-        if percentage >= COLUMN_COUNT_PERCENT {
+        if percentage >= column_count_percent {
             // Check max columns
-            if col_count > MAX_COLUMNS {
+            if col_count > max_columns {
                 return Err(CsvAnalyzerError::CsvError(CsvErrorType::TooMuchColumns));
             }
 
+            let error_row = line_counts
+                .iter()
+                .position(|&count| count != col_count)
+                .map_or(0, |idx| idx + 1);
+
             return Ok(ValidationResult {
                 columns_count: col_count,
-                error_row: 0,
+                error_row,
+                bucket: sorted_bucket(&bucket),
             });
         }
     }
 
-    Err(CsvAnalyzerError::CsvError(CsvErrorType::VariousFieldsCount))
+    Err(various_fields_count(&bucket))
 }
 
 /// Check if a string exceeds max length
-pub fn is_valid_string_size(s: &str) -> bool {
-    s.len() <= MAX_STRING_SIZE
+pub fn is_valid_string_size(s: &str, max_size: usize) -> bool {
+    s.len() <= max_size
+}
+
+/// Truncate a string to the max length, on a character boundary
+pub fn truncate_to_size(s: &str, max_size: usize) -> String {
+    let mut end = s.len().min(max_size);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
 }
 
 /// Validate a column name
-pub fn validate_column_name(name: &str, _col_idx: usize) -> Result<()> {
-    if !is_valid_string_size(name) {
+pub fn validate_column_name(name: &str, _col_idx: usize, max_size: usize) -> Result<()> {
+    if !is_valid_string_size(name, max_size) {
         return Err(CsvAnalyzerError::CsvError(CsvErrorType::ColumnLong));
     }
     Ok(())
 }
 
 /// Validate a field value
-pub fn validate_field_value(value: &str, _row_idx: usize, _col_idx: usize) -> Result<()> {
-    if !is_valid_string_size(value) {
+pub fn validate_field_value(
+    value: &str,
+    _row_idx: usize,
+    _col_idx: usize,
+    max_size: usize,
+) -> Result<()> {
+    if !is_valid_string_size(value, max_size) {
         return Err(CsvAnalyzerError::CsvError(CsvErrorType::ValueLong));
     }
     Ok(())
@@ -102,55 +156,227 @@ pub fn check_duplicate_fields(headers: &[String]) -> Result<()> {
     Ok(())
 }
 
-/// Check if sample data appears to be binary
-pub fn is_binary_data(data: &[u8]) -> bool {
-    if data.is_empty() {
-        return false;
+/// First character of a header name that is neither a letter, a digit nor
+/// one of `allowed`
+pub fn disallowed_header_char(header: &str, allowed: &str) -> Option<char> {
+    header
+        .chars()
+        .find(|&c| !c.is_alphanumeric() && !allowed.contains(c))
+}
+
+/// Find rows identical to an earlier row, ignoring case and surrounding
+/// whitespace. Returns the duplicate count and the index of the first one.
+pub fn find_duplicate_rows(rows: &[Vec<String>]) -> Option<(usize, usize)> {
+    // Normalized rows, comparing hashes only would report collisions
+    let mut seen: HashSet<Vec<String>> = HashSet::new();
+    let mut count = 0;
+    let mut first = None;
+
+    for (idx, row) in rows.iter().enumerate() {
+        let normalized = row
+            .iter()
+            .map(|field| field.trim().to_lowercase())
+            .collect();
+        if !seen.insert(normalized) {
+            count += 1;
+            first.get_or_insert(idx);
+        }
     }
 
-    // Check first line only
-    let first_line_end = data
+    first.map(|idx| (count, idx))
+}
+
+/// Whether the rows end with an empty field left by a trailing delimiter
+/// (e.g. `a,b,c,`), in at least TRAILING_EMPTY_PERCENT of them
+pub fn has_trailing_empty_column(rows: &[Vec<String>]) -> bool {
+    let trailing = rows
         .iter()
-        .position(|&b| b == b'\n')
-        .unwrap_or(data.len())
-        .min(1024);
+        .filter(|row| row.len() > 1 && row.last().is_some_and(|v| v.is_empty()))
+        .count();
+    !rows.is_empty() && trailing * 100 >= TRAILING_EMPTY_PERCENT * rows.len()
+}
 
-    let sample = &data[..first_line_end];
+/// Reconcile a first row (e.g. the header) and body rows whose column counts
+/// differ by one because only one side ends with a delimiter: the extra last
+/// field is dropped, provided it is empty on every row having it and the
+/// body rows agree on their count. Returns whether rows were changed.
+pub fn reconcile_trailing_column(rows: &mut [Vec<String>]) -> bool {
+    let Some((first, body)) = rows.split_first_mut() else {
+        return false;
+    };
+    let Some(body_len) = body.first().map(Vec::len) else {
+        return false;
+    };
+    if body.iter().any(|row| row.len() != body_len) {
+        return false;
+    }
+
+    let ends_empty = |row: &Vec<String>| row.last().is_some_and(|v| v.is_empty());
+    if body_len == first.len() + 1 && body.iter().all(ends_empty) {
+        for row in body.iter_mut() {
+            row.pop();
+        }
+        true
+    } else if first.len() == body_len + 1 && ends_empty(first) {
+        first.pop();
+        true
+    } else {
+        false
+    }
+}
 
+/// Check if sample data appears to be binary
+pub fn is_binary_data(data: &[u8]) -> bool {
+    find_binary_offset(data).is_some()
+}
+
+/// Line number (1-based) of the first line ending differently from the
+/// first line, `\r\n`, `\n` and `\r` being told apart. Line breaks between
+/// `quote` characters are part of a field and not checked.
+pub fn find_mixed_line_ending(data: &[u8], quote: Option<u8>) -> Option<usize> {
+    let mut first: Option<&[u8]> = None;
+    let mut line = 1;
+    let mut in_quotes = false;
+    let mut i = 0;
+
+    while i < data.len() {
+        let byte = data[i];
+        if Some(byte) == quote {
+            in_quotes = !in_quotes;
+        } else if byte == b'\r' || byte == b'\n' {
+            let ending: &[u8] = if byte == b'\r' && data.get(i + 1) == Some(&b'\n') {
+                i += 1;
+                b"\r\n"
+            } else if byte == b'\r' {
+                b"\r"
+            } else {
+                b"\n"
+            };
+            if !in_quotes {
+                match first {
+                    None => first = Some(ending),
+                    Some(first) if first != ending => return Some(line),
+                    Some(_) => {}
+                }
+            }
+            line += 1;
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Byte offset of the first unprintable byte when the sample appears to be
+/// binary (at least 20% of it unprintable), None for text. Line breaks and
+/// tabs are text, as are the characters of valid UTF-8 sequences.
+pub fn find_binary_offset(data: &[u8]) -> Option<usize> {
     // Skip BOM checks
-    let sample = if sample.len() >= 3 && sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
-        &sample[3..]
-    } else if sample.len() >= 2
-        && (sample.starts_with(&[0xFF, 0xFE]) || sample.starts_with(&[0xFE, 0xFF]))
-    {
-        &sample[2..]
+    let bom_len = if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        3
+    } else if data.starts_with(&[0xFF, 0xFE]) || data.starts_with(&[0xFE, 0xFF]) {
+        2
     } else {
-        sample
+        0
     };
-
+    let sample = &data[bom_len..];
     if sample.is_empty() {
-        return false;
+        return None;
     }
 
-    // Count unprintable characters
-    let unprintable_count = sample
-        .iter()
-        .filter(|&&b| b < 0x20 || b == 0xFF || (b >= 0x7F && b <= 0xA0))
-        .count();
+    let is_unprintable_byte = |b: u8| {
+        (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r')) || b == 0xFF || (0x7F..=0xA0).contains(&b)
+    };
+    let is_unprintable_char = |c: char| {
+        (c.is_ascii_control() && !matches!(c, '\t' | '\n' | '\r'))
+            || ('\u{80}'..='\u{A0}').contains(&c)
+    };
+
+    // Count unprintable characters, bytes where not valid UTF-8
+    let mut unprintable_count = 0;
+    let mut first = None;
+    let mut offset = bom_len;
+    for chunk in sample.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if is_unprintable_char(c) {
+                unprintable_count += 1;
+                first.get_or_insert(offset);
+            }
+            offset += c.len_utf8();
+        }
+        for &b in chunk.invalid() {
+            if is_unprintable_byte(b) {
+                unprintable_count += 1;
+                first.get_or_insert(offset);
+            }
+            offset += 1;
+        }
+    }
 
     // If more than 20% unprintable, consider binary
     let percentage = (unprintable_count * 100) / sample.len();
-    percentage >= 20
+    if percentage < 20 {
+        return None;
+    }
+    first
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::constants::{COLUMN_COUNT_PERCENT, MAX_COLUMNS, MAX_STRING_SIZE};
+
+    #[test]
+    fn test_trailing_empty_column() {
+        let rows = |lines: &[&str]| -> Vec<Vec<String>> {
+            lines
+                .iter()
+                .map(|l| l.split(',').map(String::from).collect())
+                .collect()
+        };
+        assert!(has_trailing_empty_column(&rows(&["a,b,", "1,2,", "3,4,"])));
+        // A single empty last value is data, not a trailing delimiter
+        assert!(!has_trailing_empty_column(&rows(&["a,b", "1,", "3,4"])));
+        assert!(!has_trailing_empty_column(&rows(&["a", ""])));
+        assert!(!has_trailing_empty_column(&[]));
+    }
+
+    #[test]
+    fn test_reconcile_trailing_column() {
+        let rows = |lines: &[&str]| -> Vec<Vec<String>> {
+            lines
+                .iter()
+                .map(|l| l.split(',').map(String::from).collect())
+                .collect()
+        };
+
+        // Body rows end with a delimiter, the header doesn't
+        let mut body = rows(&["a,b,c", "1,2,3,", "4,5,6,"]);
+        assert!(reconcile_trailing_column(&mut body));
+        assert_eq!(body, rows(&["a,b,c", "1,2,3", "4,5,6"]));
+
+        // The header ends with a delimiter, the body rows don't
+        let mut header = rows(&["a,b,c,", "1,2,3", "4,5,6"]);
+        assert!(reconcile_trailing_column(&mut header));
+        assert_eq!(header, rows(&["a,b,c", "1,2,3", "4,5,6"]));
+
+        // The extra field holds data, or the body rows disagree
+        assert!(!reconcile_trailing_column(&mut rows(&[
+            "a,b", "1,2,3", "4,5,"
+        ])));
+        assert!(!reconcile_trailing_column(&mut rows(&[
+            "a,b", "1,2,", "4,5"
+        ])));
+        assert!(!reconcile_trailing_column(&mut rows(&["a,b", "1,2"])));
+        assert!(!reconcile_trailing_column(&mut rows(&["a,b,"])));
+    }
 
     #[test]
     fn test_validate_consistent_columns() {
         let lines = vec!["a,b,c", "1,2,3", "x,y,z"];
-        let result = validate_columns_count(&lines, ',', '"').unwrap();
+        let result =
+            validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, COLUMN_COUNT_PERCENT)
+                .unwrap();
         assert_eq!(result.columns_count, 3);
     }
 
@@ -158,15 +384,92 @@ mod tests {
     fn test_validate_inconsistent_columns() {
         let lines = vec!["a,b,c", "1,2", "x,y,z,w"];
         // With 3 different counts and only 1 occurrence each, no majority
-        let result = validate_columns_count(&lines, ',', '"');
+        let result =
+            validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, COLUMN_COUNT_PERCENT);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_reports_first_ragged_row() {
+        let mut lines = vec!["a,b,c"; 10];
+        lines[4] = "1,2";
+        let result =
+            validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, COLUMN_COUNT_PERCENT)
+                .unwrap();
+        assert_eq!(result.columns_count, 3);
+        assert_eq!(result.error_row, 5);
+    }
+
+    #[test]
+    fn test_validate_custom_thresholds() {
+        let lines = vec!["a,b,c,d", "1,2,3,4", "5,6,7,8", "x,y"];
+        // 3 of 4 lines agree: too few at 90%, enough at 75%
+        assert!(validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, 90).is_err());
+        let result = validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, 75).unwrap();
+        assert_eq!(result.columns_count, 4);
+        assert_eq!(result.error_row, 4);
+
+        let result = validate_columns_count(&lines, ',', '"', None, 3, 75);
+        assert!(matches!(
+            result,
+            Err(CsvAnalyzerError::CsvError(CsvErrorType::TooMuchColumns))
+        ));
+    }
+
+    #[test]
+    fn test_column_count_distribution() {
+        let lines = vec!["a,b,c,d", "1,2,3,4", "5,6,7,8", "x,y"];
+        let result = validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, 75).unwrap();
+        assert_eq!(result.bucket, vec![(2, 1), (4, 3)]);
+
+        let error = validate_columns_count(&lines, ',', '"', None, MAX_COLUMNS, 90).unwrap_err();
+        assert_eq!(error.error_type(), CsvErrorType::VariousFieldsCount);
+        assert_eq!(
+            error.to_string(),
+            "CSV error: Can not determine the number of columns. \
+             Too much variation in column count (2 cols: 1 rows, 4 cols: 3 rows)"
+        );
+    }
+
+    #[test]
+    fn test_string_size_custom_limit() {
+        let value = "x".repeat(1200);
+        assert!(!is_valid_string_size(&value, 1000));
+        assert!(is_valid_string_size(&value, 4000));
+        assert_eq!(truncate_to_size(&value, 500).len(), 500);
+        assert!(validate_field_value(&value, 0, 0, 1000).is_err());
+        assert!(validate_field_value(&value, 0, 0, 4000).is_ok());
+        assert!(validate_column_name("name", 0, 3).is_err());
+    }
+
     #[test]
     fn test_is_valid_string_size() {
-        assert!(is_valid_string_size("hello"));
+        assert!(is_valid_string_size("hello", MAX_STRING_SIZE));
         let long_string = "x".repeat(MAX_STRING_SIZE + 1);
-        assert!(!is_valid_string_size(&long_string));
+        assert!(!is_valid_string_size(&long_string, MAX_STRING_SIZE));
+    }
+
+    #[test]
+    fn test_truncate_to_size() {
+        assert_eq!(truncate_to_size("hello", MAX_STRING_SIZE), "hello");
+        assert_eq!(
+            truncate_to_size(&"x".repeat(MAX_STRING_SIZE + 5), MAX_STRING_SIZE).len(),
+            MAX_STRING_SIZE
+        );
+        // A 2-byte character straddling the limit is dropped whole
+        let s = format!("{}é", "x".repeat(MAX_STRING_SIZE - 1));
+        assert_eq!(
+            truncate_to_size(&s, MAX_STRING_SIZE),
+            "x".repeat(MAX_STRING_SIZE - 1)
+        );
+    }
+
+    #[test]
+    fn test_disallowed_header_char() {
+        assert_eq!(disallowed_header_char("first_name", "_"), None);
+        assert_eq!(disallowed_header_char("Prénom", ""), None);
+        assert_eq!(disallowed_header_char("order #", "_ "), Some('#'));
+        assert_eq!(disallowed_header_char("first name", "_"), Some(' '));
     }
 
     #[test]
@@ -182,6 +485,23 @@ mod tests {
         assert!(check_duplicate_fields(&dup_headers).is_err());
     }
 
+    #[test]
+    fn test_find_duplicate_rows() {
+        let row = |fields: &[&str]| fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let rows = vec![
+            row(&["a@example.com", "Ann"]),
+            row(&["b@example.com", "Bob"]),
+            row(&["A@example.com", "ann "]),
+            row(&["b@example.com", "Bob"]),
+        ];
+        assert_eq!(find_duplicate_rows(&rows), Some((2, 2)));
+        assert_eq!(find_duplicate_rows(&rows[..2]), None);
+
+        // Same characters split into other fields
+        let rows = vec![row(&["ab", "c"]), row(&["a", "bc"]), row(&["abc"])];
+        assert_eq!(find_duplicate_rows(&rows), None);
+    }
+
     #[test]
     fn test_is_binary_data() {
         let text = b"hello,world\n";
@@ -190,4 +510,53 @@ mod tests {
         let binary = vec![0x00, 0x01, 0x02, 0x03, 0x04, 0x05];
         assert!(is_binary_data(&binary));
     }
+
+    #[test]
+    fn test_find_mixed_line_ending() {
+        assert_eq!(
+            find_mixed_line_ending(b"a,b\r\nc,d\r\ne,f\r\n", Some(b'"')),
+            None
+        );
+        assert_eq!(find_mixed_line_ending(b"a,b\nc,d\ne,f", Some(b'"')), None);
+        assert_eq!(
+            find_mixed_line_ending(b"a,b\r\nc,d\r\ne,f\ng,h\r\n", Some(b'"')),
+            Some(3)
+        );
+        assert_eq!(
+            find_mixed_line_ending(b"a,b\nc,d\re,f\n", Some(b'"')),
+            Some(2)
+        );
+        // Excel keeps line feeds inside quoted values of CRLF files
+        assert_eq!(
+            find_mixed_line_ending(b"a,\"b\nc\"\r\nd,e\r\n", Some(b'"')),
+            None
+        );
+        assert_eq!(
+            find_mixed_line_ending(b"a,\"b\nc\"\r\nd,e\r\n", None),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_find_binary_offset() {
+        assert_eq!(find_binary_offset(b"hello,world\n"), None);
+        assert_eq!(find_binary_offset(b"ab\x00\x01\x02\x03\n"), Some(2));
+        // Offsets count the BOM
+        assert_eq!(
+            find_binary_offset(b"\xEF\xBB\xBFab\x00\x01\x02\x03\n"),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn test_binary_after_first_line() {
+        let mut data = b"email,name,photo\n".to_vec();
+        data.extend((0..64u8).cycle().take(200));
+        assert_eq!(find_binary_offset(&data), Some(17));
+
+        // Short lines, tabs and non-ASCII text are not binary
+        assert_eq!(find_binary_offset(b"a\tb\r\n1\t2\r\n3\t4\r\n"), None);
+        let text = "имя,город\nАлександр,Москва\nРенée,Zürich\n".repeat(5);
+        assert_eq!(find_binary_offset(text.as_bytes()), None);
+    }
 }
@@ -0,0 +1,136 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const BIN: &str = env!("CARGO_BIN_EXE_csvanalyzertool");
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// SQLite metadata database, so the CLI runs without PostgreSQL
+fn sqlite_db(dir: &Path) -> String {
+    let path = dir.join("meta.db");
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE contact_meta (name TEXT, datatype INTEGER, namespace INTEGER);
+         INSERT INTO contact_meta VALUES ('name', 0, 0);",
+    )
+    .unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn run(extra_args: &[&str]) -> Output {
+    let file = fixture("test_simple.csv");
+    run_with_input(&["--filename", &file], extra_args)
+}
+
+fn run_with_input(input_args: &[&str], extra_args: &[&str]) -> Output {
+    let dir = tempfile::tempdir().unwrap();
+    let db = sqlite_db(dir.path());
+    Command::new(BIN)
+        .args(["--akid", "1", "--locale", "en_US", "--sqlite-db", &db])
+        .args(input_args)
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_debug_output() {
+    let output = run(&["--debug"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("DEBUG: Charset:"));
+    assert!(stderr.contains("DEBUG: Contact metadata query"));
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("DEBUG:"));
+}
+
+#[test]
+fn test_data_base64() {
+    let content = std::fs::read(fixture("test_simple.csv")).unwrap();
+    let encoded = STANDARD.encode(content);
+
+    let from_file = run(&[]);
+    let from_base64 = run_with_input(&["--data-base64", &encoded], &[]);
+    assert!(from_base64.status.success());
+    assert_eq!(from_base64.stdout, from_file.stdout);
+}
+
+#[test]
+fn test_msgpack_output() {
+    let json = run(&[]);
+    let msgpack = run(&["--msgpack"]);
+    assert!(msgpack.status.success());
+
+    let decoded: serde_json::Value = rmp_serde::from_slice(&msgpack.stdout).unwrap();
+    let expected: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn test_detect_only() {
+    // No database needed
+    let output = Command::new(BIN)
+        .args(["--akid", "1", "--locale", "en_US", "--detect-only"])
+        .args(["--filename", &fixture("test_simple.csv")])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "Charset": "ansi",
+            "FieldSeparator": "2C",
+            "TextDelimiter": "22",
+            "SkipHeader": true
+        })
+    );
+}
+
+#[test]
+fn test_quiet_suppresses_warnings() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing_db = dir.path().join("missing.db");
+    let run_without_db = |extra_args: &[&str]| {
+        Command::new(BIN)
+            .args(["--akid", "1", "--locale", "en_US"])
+            .args(["--filename", &fixture("test_simple.csv")])
+            .args(["--sqlite-db", missing_db.to_str().unwrap()])
+            .args(extra_args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run_without_db(&[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning:"));
+
+    let quiet = run_without_db(&["--quiet"]);
+    assert!(quiet.status.success());
+    assert!(quiet.stderr.is_empty());
+    assert_eq!(quiet.stdout, output.stdout);
+}
+
+#[test]
+fn test_options_file_overridden() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = dir.path().join("options.toml");
+    std::fs::write(&options, "infer_types = false\nrequire_email = false\n").unwrap();
+    let options = options.to_str().unwrap();
+
+    let default = run(&[]);
+    let from_file = run(&["--options", options]);
+    assert!(from_file.status.success());
+    assert_ne!(from_file.stdout, default.stdout);
+
+    let overridden = run(&["--options", options, "--infer-types", "--require-email"]);
+    assert!(overridden.status.success());
+    assert_eq!(overridden.stdout, default.stdout);
+}